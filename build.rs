@@ -1,3 +1,166 @@
+use std::{env, fmt::Write, fs, path::Path};
+
+/// Top-level object of the device manifest.
+struct Object {
+    name: String,
+    kind: String,
+    address: Option<u32>,
+    size_bits: Option<u32>,
+    size_bits_in: Option<u32>,
+    size_bits_out: Option<u32>,
+}
+
+/// Collect the objects of the manifest, relying on its flat layout of two-space indented properties.
+fn objects(manifest: &str) -> Vec<Object> {
+    let mut objects: Vec<Object> = Vec::new();
+
+    for line in manifest.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        if !line.starts_with(' ') {
+            if let Some(name) = line.strip_suffix(':') {
+                objects.push(Object {
+                    name: name.to_string(),
+                    kind: String::new(),
+                    address: None,
+                    size_bits: None,
+                    size_bits_in: None,
+                    size_bits_out: None,
+                });
+            }
+            continue;
+        }
+
+        let (Some(object), Some(property)) = (objects.last_mut(), line.strip_prefix("  ")) else {
+            continue;
+        };
+        if property.starts_with(' ') {
+            continue;
+        }
+        let Some((key, value)) = property.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        let number = || match value.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        };
+
+        match key {
+            "type" => object.kind = value.to_string(),
+            "address" => object.address = number(),
+            "size_bits" => object.size_bits = number(),
+            "size_bits_in" => object.size_bits_in = number(),
+            "size_bits_out" => object.size_bits_out = number(),
+            _ => {}
+        }
+    }
+
+    objects
+}
+
+/// The name device-driver gives the field set of `name`, e.g. `SET_TX` becomes `SetTx`.
+fn field_set(name: &str) -> String {
+    name.split('_')
+        .map(|word| word[..1].to_string() + &word[1..].to_ascii_lowercase())
+        .collect()
+}
+
 fn main() {
     println!("cargo:rerun-if-changed=device.yaml");
+
+    let manifest = fs::read_to_string("device.yaml").unwrap();
+    let objects = objects(&manifest);
+
+    // Name tables for annotating raw SPI traffic, see `src/names.rs`.
+    let mut names = String::new();
+    writeln!(names, "pub const COMMANDS: &[(u8, &str, bool)] = &[").unwrap();
+    for object in objects.iter().filter(|o| o.kind == "command") {
+        if let Some(address) = object.address {
+            let read = object.size_bits_out.is_some();
+            writeln!(names, "    ({address:#04X}, {:?}, {read}),", object.name).unwrap();
+        }
+    }
+    writeln!(names, "];").unwrap();
+
+    writeln!(names, "pub const REGISTERS: &[(u16, u32, &str)] = &[").unwrap();
+    for object in objects.iter().filter(|o| o.kind == "register") {
+        if let (Some(address), Some(size_bits)) = (object.address, object.size_bits) {
+            writeln!(
+                names,
+                "    ({address:#05X}, {size_bits}, {:?}),",
+                object.name
+            )
+            .unwrap();
+        }
+    }
+    writeln!(names, "];").unwrap();
+
+    // Field decoding for the tracing in `ll::Interface`. Only the first command of each opcode is
+    // decoded, like in `command`, registers sharing an address are told apart by their size.
+    writeln!(names, "#[cfg(any(test, feature = \"trace\"))]").unwrap();
+    writeln!(
+        names,
+        "pub(crate) fn command_fields(opcode: u8, input: &[u8], output: &[u8]) {{"
+    )
+    .unwrap();
+    writeln!(names, "    match opcode {{").unwrap();
+    let mut opcodes = Vec::new();
+    for object in objects.iter().filter(|o| o.kind == "command") {
+        let Some(address) = object.address else {
+            continue;
+        };
+        if opcodes.contains(&address) {
+            continue;
+        }
+        opcodes.push(address);
+
+        let decode = |bits: u32, data: &str, suffix: &str| {
+            format!(
+                "if let Ok(bytes) = <[u8; {}]>::try_from({data}) {{ trace!(\"  {{:?}}\", crate::ll::field_sets::{}{suffix}::from(bytes)); }}",
+                bits.div_ceil(8),
+                field_set(&object.name)
+            )
+        };
+        let mut arm = String::new();
+        if let Some(bits) = object.size_bits_in {
+            arm += &decode(bits, "input", "FieldsIn");
+        }
+        if let Some(bits) = object.size_bits_out {
+            arm += &decode(bits, "output", "FieldsOut");
+        }
+        if !arm.is_empty() {
+            writeln!(names, "        {address:#04X} => {{ {arm} }}").unwrap();
+        }
+    }
+    writeln!(names, "        _ => {{}}").unwrap();
+    writeln!(names, "    }}").unwrap();
+    writeln!(names, "}}").unwrap();
+
+    writeln!(names, "#[cfg(any(test, feature = \"trace\"))]").unwrap();
+    writeln!(
+        names,
+        "pub(crate) fn register_fields(address: u16, data: &[u8]) {{"
+    )
+    .unwrap();
+    writeln!(names, "    match address {{").unwrap();
+    for object in objects.iter().filter(|o| o.kind == "register") {
+        if let (Some(address), Some(size_bits)) = (object.address, object.size_bits) {
+            writeln!(
+                names,
+                "        {address:#05X} if data.len() == {bytes} => trace!(\"  {{:?}}\", crate::ll::field_sets::{}::from(<[u8; {bytes}]>::try_from(data).unwrap())),",
+                field_set(&object.name),
+                bytes = size_bits.div_ceil(8),
+            )
+            .unwrap();
+        }
+    }
+    writeln!(names, "        _ => {{}}").unwrap();
+    writeln!(names, "    }}").unwrap();
+    writeln!(names, "}}").unwrap();
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("names.rs"), names).unwrap();
 }
@@ -0,0 +1,69 @@
+//! Decode a Logic SPI analyzer CSV export of SX128x traffic.
+//!
+//! ```text
+//! sx128x-capture decode <export.csv> [--gap-us N]
+//! sx128x-capture test <export.csv> [--gap-us N]
+//! ```
+//!
+//! `decode` prints one annotated command per transaction, `test` prints the expectations for a replay test.
+
+use std::{env, fs, process::ExitCode, time::Duration};
+
+use sx128x::capture::{self, DEFAULT_GAP};
+
+const USAGE: &str = "usage: sx128x-capture <decode|test> <export.csv> [--gap-us N]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (mode, path) = match args.as_slice() {
+        [mode, path, ..] => (mode.as_str(), path),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let gap = match args.get(2..) {
+        Some([flag, value]) if flag == "--gap-us" => match value.parse() {
+            Ok(us) => Duration::from_micros(us),
+            Err(_) => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Some([]) | None => DEFAULT_GAP,
+        Some(_) => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let csv = match fs::read_to_string(path) {
+        Ok(csv) => csv,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let frames = match capture::parse_csv(&csv, gap) {
+        Ok(frames) => frames,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match mode {
+        "decode" => {
+            for frame in &frames {
+                println!("{:>12.6} {}", frame.start_s, capture::decode(frame));
+            }
+        }
+        "test" => print!("{}", capture::test_source(&frames)),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,380 @@
+//! Decoding and replay of logic analyzer captures of the SPI bus.
+//!
+//! Reads the SPI analyzer table exported as CSV by Saleae Logic 2 (and the older Logic 1 format).
+//! The `.sal` captures in `docs/` use an undocumented format, open them in Logic 2 and export the
+//! SPI analyzer results first.
+//!
+//! Each [Frame] is one NSS-delimited transaction. Captures without an enable channel have no
+//! transaction boundaries, for those consecutive bytes are grouped unless separated by a gap.
+//!
+//! [decode] annotates a frame with the command and register names from `device.yaml`,
+//! [ReplaySpi] plays a capture back to the driver, and [test_source] turns it into
+//! expectations for the tests in `src/test`.
+
+extern crate std;
+
+use core::{fmt, time::Duration};
+use std::{format, string::String, vec::Vec};
+
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation};
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::names;
+
+/// Gap after which bytes are considered part of a new transaction, when the capture has no enable channel.
+pub const DEFAULT_GAP: Duration = Duration::from_micros(20);
+
+/// One SPI transaction.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Frame {
+    /// Start of the first byte, in seconds since the start of the capture.
+    pub start_s: f64,
+    pub mosi: Vec<u8>,
+    pub miso: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParseError {
+    /// The header does not match a known export format.
+    Header,
+    /// The line at this (1-based) number could not be parsed.
+    Line(usize),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Header => write!(
+                f,
+                "unknown CSV header, expected a Logic SPI analyzer export"
+            ),
+            ParseError::Line(line) => write!(f, "could not parse line {line}"),
+        }
+    }
+}
+
+fn parse_byte(field: &str) -> Option<u8> {
+    match field.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => field.parse().ok(),
+    }
+}
+
+/// Parse the CSV export of a Logic SPI analyzer into frames.
+pub fn parse_csv(csv: &str, gap: Duration) -> Result<Vec<Frame>, ParseError> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or(ParseError::Header)?;
+    let split = |line: &str| -> Vec<String> {
+        line.split(',')
+            .map(|field| field.trim().trim_matches('"').to_ascii_lowercase())
+            .collect()
+    };
+
+    let header = split(header);
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let time = column(&["start_time", "time [s]"]).ok_or(ParseError::Header)?;
+    let mosi = column(&["mosi"]).ok_or(ParseError::Header)?;
+    let miso = column(&["miso"]).ok_or(ParseError::Header)?;
+    // Logic 2 marks NSS edges with enable/disable rows, Logic 1 numbers the packets.
+    let kind = column(&["type"]);
+    let packet_id = column(&["packet id"]);
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut open = false;
+    let mut last_id = None;
+    let mut last_time = f64::NEG_INFINITY;
+
+    for (number, line) in lines {
+        let fields = split(line);
+        let field = |index: usize| fields.get(index).map(String::as_str).unwrap_or("");
+        let error = ParseError::Line(number + 1);
+        let start_s: f64 = field(time).parse().map_err(|_| error.clone())?;
+
+        match kind.map(field) {
+            Some("enable") => {
+                frames.push(Frame {
+                    start_s,
+                    mosi: Vec::new(),
+                    miso: Vec::new(),
+                });
+                open = true;
+                continue;
+            }
+            Some("disable") => {
+                open = false;
+                continue;
+            }
+            Some("result") | None => {}
+            Some(_) => continue,
+        }
+
+        let new_frame = match packet_id.map(field) {
+            Some(id) if !id.is_empty() => last_id.replace(String::from(id)).as_deref() != Some(id),
+            _ => !open && start_s - last_time > gap.as_secs_f64(),
+        };
+        last_time = start_s;
+        if new_frame || frames.is_empty() {
+            frames.push(Frame {
+                start_s,
+                mosi: Vec::new(),
+                miso: Vec::new(),
+            });
+        }
+
+        let frame = frames.last_mut().unwrap();
+        frame
+            .mosi
+            .push(parse_byte(field(mosi)).ok_or(error.clone())?);
+        frame.miso.push(parse_byte(field(miso)).ok_or(error)?);
+    }
+
+    frames.retain(|frame| !frame.mosi.is_empty());
+    Ok(frames)
+}
+
+/// What a frame does.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Access {
+    Command {
+        opcode: u8,
+        name: Option<&'static str>,
+        read: bool,
+    },
+    WriteRegister {
+        address: u16,
+        name: Option<(&'static str, u16)>,
+    },
+    ReadRegister {
+        address: u16,
+        name: Option<(&'static str, u16)>,
+    },
+    WriteBuffer {
+        offset: u8,
+    },
+    ReadBuffer {
+        offset: u8,
+    },
+}
+
+/// A frame annotated with the command it carries.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Decoded {
+    pub access: Access,
+    /// The arguments written, or the data read back.
+    pub data: Vec<u8>,
+}
+
+/// Annotate `frame` with the command and register names from `device.yaml`.
+pub fn decode(frame: &Frame) -> Decoded {
+    let byte = |index: usize| frame.mosi.get(index).copied().unwrap_or(0);
+    let tail = |bytes: &[u8], from: usize| bytes.get(from..).unwrap_or_default().to_vec();
+    let address = u16::from_be_bytes([byte(1), byte(2)]);
+
+    let (access, data) = match byte(0) {
+        0x18 => (
+            Access::WriteRegister {
+                address,
+                name: names::register(address),
+            },
+            tail(&frame.mosi, 3),
+        ),
+        0x19 => (
+            Access::ReadRegister {
+                address,
+                name: names::register(address),
+            },
+            tail(&frame.miso, 4),
+        ),
+        0x1A => (
+            Access::WriteBuffer { offset: byte(1) },
+            tail(&frame.mosi, 2),
+        ),
+        0x1B => (Access::ReadBuffer { offset: byte(1) }, tail(&frame.miso, 3)),
+        opcode => {
+            let (name, read) = match names::command(opcode) {
+                Some((name, read)) => (Some(name), read),
+                None => (None, false),
+            };
+            let data = if opcode == 0xC0 {
+                // The status is shifted out on every byte, the last one is the most recent.
+                tail(&frame.miso, frame.miso.len().saturating_sub(1))
+            } else if read {
+                tail(&frame.miso, 2)
+            } else {
+                tail(&frame.mosi, 1)
+            };
+            (Access::Command { opcode, name, read }, data)
+        }
+    };
+
+    Decoded { access, data }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, data: &[u8]) -> fmt::Result {
+    for byte in data {
+        write!(f, " {byte:02X}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let register = |f: &mut fmt::Formatter<'_>, name: Option<(&str, u16)>| match name {
+            Some((name, 0)) => write!(f, " ({name})"),
+            Some((name, offset)) => write!(f, " ({name}+{offset})"),
+            None => Ok(()),
+        };
+
+        match self.access {
+            Access::Command { opcode, name, read } => {
+                match name {
+                    Some(name) => write!(f, "{name}")?,
+                    None => write!(f, "UNKNOWN {opcode:#04X}")?,
+                }
+                if read {
+                    write!(f, " ->")?;
+                }
+            }
+            Access::WriteRegister { address, name } => {
+                write!(f, "WRITE_REGISTER {address:#06X}")?;
+                register(f, name)?;
+            }
+            Access::ReadRegister { address, name } => {
+                write!(f, "READ_REGISTER {address:#06X}")?;
+                register(f, name)?;
+                write!(f, " ->")?;
+            }
+            Access::WriteBuffer { offset } => write!(f, "WRITE_BUFFER @{offset:#04X}")?,
+            Access::ReadBuffer { offset } => write!(f, "READ_BUFFER @{offset:#04X} ->")?,
+        }
+        write_hex(f, &self.data)
+    }
+}
+
+fn rust_bytes(data: &[u8]) -> String {
+    let bytes: Vec<String> = data.iter().map(|byte| format!("{byte:#04X}")).collect();
+    format!("&[{}]", bytes.join(", "))
+}
+
+/// Render `frames` as an expectation array using the helpers in `src/test/mod.rs`.
+pub fn test_source(frames: &[Frame]) -> String {
+    let mut source = String::from("let expectations = [\n");
+
+    for frame in frames {
+        let decoded = decode(frame);
+        let data = rust_bytes(&decoded.data);
+        let call = match decoded.access {
+            Access::Command { opcode: 0xC0, .. } if frame.mosi.len() == 2 => String::from("wake()"),
+            Access::Command {
+                opcode, read: true, ..
+            } => format!("cmd_r({opcode:#04X}, {data})"),
+            Access::Command { opcode, .. } if decoded.data.is_empty() => {
+                format!("cmd({opcode:#04X})")
+            }
+            Access::Command { opcode, .. } => format!("cmd_w({opcode:#04X}, {data})"),
+            Access::WriteRegister { address, .. } => format!("reg_w({address:#05X}, {data})"),
+            Access::ReadRegister { address, .. } => format!("reg_r({address:#05X}, {data})"),
+            Access::WriteBuffer { offset } => format!("buf_w({offset:#04X}, {data})"),
+            Access::ReadBuffer { offset } => format!("buf_r({offset:#04X}, {data})"),
+        };
+        source += &format!("    // {decoded}\n    {call},\n");
+    }
+
+    source += "];\n";
+    source
+}
+
+/// The driver diverged from the capture.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ReplayError {
+    /// Index of the frame that was expected.
+    pub index: usize,
+    /// MOSI of that frame, or empty if the capture ended.
+    pub expected: Vec<u8>,
+    pub actual: Vec<u8>,
+}
+
+impl embedded_hal::spi::Error for ReplayError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// SPI device playing back a capture, checking MOSI and answering with the captured MISO.
+pub struct ReplaySpi {
+    frames: Vec<Frame>,
+    next: usize,
+}
+
+impl ReplaySpi {
+    pub fn new(frames: Vec<Frame>) -> Self {
+        Self { frames, next: 0 }
+    }
+
+    /// The number of frames not played back yet.
+    pub fn remaining(&self) -> usize {
+        self.frames.len() - self.next
+    }
+}
+
+impl ErrorType for ReplaySpi {
+    type Error = ReplayError;
+}
+
+impl SpiDevice for ReplaySpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), ReplayError> {
+        let index = self.next;
+        let empty = Frame {
+            start_s: 0.0,
+            mosi: Vec::new(),
+            miso: Vec::new(),
+        };
+        let frame = self.frames.get(index).unwrap_or(&empty);
+        let miso = |position: usize| frame.miso.get(position).copied().unwrap_or(0);
+        let mut mosi = Vec::new();
+
+        for operation in operations {
+            match operation {
+                Operation::Read(words) => {
+                    for word in words.iter_mut() {
+                        *word = miso(mosi.len());
+                        mosi.push(0x00);
+                    }
+                }
+                Operation::Write(words) => mosi.extend_from_slice(words),
+                Operation::Transfer(read, write) => {
+                    for index in 0..read.len().max(write.len()) {
+                        if let Some(word) = read.get_mut(index) {
+                            *word = miso(mosi.len());
+                        }
+                        mosi.push(write.get(index).copied().unwrap_or(0x00));
+                    }
+                }
+                Operation::TransferInPlace(words) => {
+                    for word in words.iter_mut() {
+                        let written = *word;
+                        *word = miso(mosi.len());
+                        mosi.push(written);
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+
+        if mosi != frame.mosi {
+            return Err(ReplayError {
+                index,
+                expected: frame.mosi.clone(),
+                actual: mosi,
+            });
+        }
+        self.next += 1;
+        Ok(())
+    }
+}
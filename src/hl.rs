@@ -5,47 +5,66 @@ use crate::{
     ll::{self, PacketType},
 };
 
+pub mod auto_tx;
+pub mod buffer;
+pub mod calibration;
+pub mod chip;
+pub mod context;
+pub mod duty_cycle;
+pub mod frequency;
+pub mod frontend;
+pub mod gain;
+pub mod hopping;
 pub mod irq;
 pub mod lora;
-
+#[cfg(any(test, feature = "multi"))]
+pub mod multi;
+#[cfg(any(test, feature = "multi"))]
+pub mod preempt;
+pub mod scan;
+#[cfg(any(test, feature = "embassy"))]
+pub mod task;
+pub mod test_modes;
+
+use buffer::{BufferBases, Received};
+use calibration::{CalibrationParams, RECALIBRATION_THRESHOLD_C, SleepParams};
+use chip::{ChipInfo, ChipVariant};
+use device_driver::AsyncBufferInterface;
 use embedded_hal::digital::OutputPin;
 use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+pub use frequency::{ChannelPlan, Frequency};
+use frontend::{NoFrontend, RfFrontend, RfState};
 use irq::Irq;
-pub use ll::RampTime;
+pub use ll::{RampTime, RegulatorType};
 use lora::{LoRaModemParams, LoRaModulationParams, LoRaPacketParams};
 
+/// Errors of high level operations that can fail for other reasons than the SPI bus.
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct Frequency {
-    raw: [u8; 3],
+pub enum Error<E> {
+    Spi(E),
+    /// The firmware version register holds an unknown value, usually due to a wiring issue.
+    UnknownFirmware(u16),
+    /// The chip has not been probed yet, see [SX128X::probe].
+    NotProbed,
+    /// The operation is not supported by the probed chip variant.
+    Unsupported,
+    /// The chip reported a failure after `CALIBRATE`.
+    CalibrationFailed,
+    /// No payload was loaded with [SX128X::load_tx], or it has been overwritten since.
+    NothingLoaded,
+    /// A payload does not fit in the part of the data buffer reserved for it.
+    TooLong,
 }
 
-impl Frequency {
-    pub const fn new(freq_hz: u64) -> Self {
-        const CRYSTAL_FREQ_HZ: u64 = 52_000_000u64;
-        const PLL_STEPS: u64 = 2u64.pow(18);
-
-        let freq_steps = ((freq_hz * PLL_STEPS) / CRYSTAL_FREQ_HZ) as u32;
-        let array = freq_steps.to_be_bytes();
-
-        Self {
-            raw: [array[1], array[2], array[3]],
-        }
-    }
-
-    pub const fn from_bytes(raw: [u8; 3]) -> Self {
-        Self { raw }
-    }
-
-    pub fn as_bytes(&self) -> [u8; 3] {
-        self.raw
-    }
-}
-
-impl Default for Frequency {
-    fn default() -> Self {
-        Self::new(2_440_000_000)
-    }
+/// How long a receive waits for a packet, in `count` steps of `period_base`.
+///
+/// `count` must lie within 1..=0xFFFE, 0 and 0xFFFF select single and continuous mode.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RxTimeout {
+    pub period_base: ll::RxTimeoutStep,
+    pub count: u16,
 }
 
 #[derive(Copy, Clone, PartialEq, Default, Debug)]
@@ -55,19 +74,31 @@ pub struct TxParams {
     pub ramp_time: ll::RampTime,
 }
 
-pub struct SX128X<T, BUSY, DIO, NRESET, DELAY>
+pub struct SX128X<T, BUSY, DIO, NRESET, DELAY, FE = NoFrontend>
 where
     T: SpiDevice,
     BUSY: Wait<Error = Infallible>,
     DIO: Wait<Error = Infallible>,
     NRESET: OutputPin<Error = Infallible>,
     DELAY: DelayNs,
+    FE: RfFrontend,
 {
     ll: ll::Device<ll::Interface<T, BUSY>>,
     nreset: NRESET,
     dio1: DIO,
     delay: DELAY,
+    frontend: FE,
     params: LoRaModemParams,
+    chip: Option<ChipInfo>,
+    calibration: CalibrationParams,
+    calibration_temperature: Option<i8>,
+    sleep: Option<SleepParams>,
+    auto_fs: bool,
+    buffer_bases: BufferBases,
+    /// Length of the payload loaded at the TX base address.
+    tx_loaded: Option<u8>,
+    /// A TX or RX operation was started and has not completed, its future may have been dropped.
+    in_flight: bool,
 }
 
 impl<
@@ -77,7 +108,7 @@ impl<
     NRESET: OutputPin<Error = Infallible>,
     DELAY: DelayNs,
     E,
-> SX128X<T, BUSY, DIO, NRESET, DELAY>
+> SX128X<T, BUSY, DIO, NRESET, DELAY, NoFrontend>
 {
     pub fn new(
         t: T,
@@ -86,13 +117,45 @@ impl<
         nreset: NRESET,
         delay: DELAY,
         params: LoRaModemParams,
+    ) -> Self {
+        Self::new_with_frontend(t, busy, dio1, nreset, delay, NoFrontend, params)
+    }
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    pub fn new_with_frontend(
+        t: T,
+        busy: BUSY,
+        dio1: DIO,
+        nreset: NRESET,
+        delay: DELAY,
+        frontend: FE,
+        params: LoRaModemParams,
     ) -> Self {
         Self {
             ll: ll::Device::new(ll::Interface::new(t, busy)),
             nreset,
             dio1,
             delay,
+            frontend,
             params,
+            chip: None,
+            calibration: CalibrationParams::all(),
+            calibration_temperature: None,
+            sleep: None,
+            auto_fs: false,
+            buffer_bases: BufferBases::default(),
+            tx_loaded: None,
+            in_flight: false,
         }
     }
 
@@ -101,14 +164,29 @@ impl<
         self.delay.delay_ms(10).await;
         let _ = self.nreset.set_high();
         self.delay.delay_ms(10).await;
+        self.frontend.set_state(RfState::Standby);
+        self.tx_loaded = None;
+        self.in_flight = false;
     }
 
     pub fn ll(&mut self) -> &mut ll::Device<ll::Interface<T, BUSY>> {
         &mut self.ll
     }
 
+    /// Log the SPI traffic with the `trace` feature and `defmt` or `log`, see [ll::Verbosity].
+    #[cfg(any(test, feature = "trace"))]
+    pub fn set_verbosity(&mut self, verbosity: ll::Verbosity) {
+        self.ll.interface.set_verbosity(verbosity);
+    }
+
+    pub fn frontend(&mut self) -> &mut FE {
+        &mut self.frontend
+    }
+
     pub async fn configure(&mut self) -> Result<(), E> {
+        self.recover().await?;
         self.set_standbyrc().await?;
+        self.set_regulator_mode(self.params.regulator).await?;
         self.set_rf_frequency(self.params.frequency).await?;
         self.set_packet_type(PacketType::LoRa).await?;
         self.set_modulation_params(self.params.modulation_params)
@@ -118,28 +196,250 @@ impl<
         Ok(())
     }
 
-    pub async fn calibrate(&mut self) -> Result<(), E> {
+    /// Switch to another RF frequency, keeping it for subsequent calls to [SX128X::configure].
+    pub async fn set_frequency(&mut self, frequency: Frequency) -> Result<(), E> {
+        self.recover().await?;
+        self.params.frequency = frequency;
+        self.set_rf_frequency(frequency).await
+    }
+
+    /// Enter standby, either on the RC oscillator or the crystal oscillator.
+    ///
+    /// STDBY_XOSC consumes more power, but shortens the transition to FS, TX and RX.
+    pub async fn standby(&mut self, config: ll::StandbyConfig) -> Result<(), E> {
+        self.recover().await?;
+        self.enter_standby(config).await
+    }
+
+    /// Enter standby without ending the current operation, see [SX128X::standby].
+    async fn enter_standby(&mut self, config: ll::StandbyConfig) -> Result<(), E> {
+        self.frontend.set_state(RfState::Standby);
+        self.ll
+            .set_standby()
+            .dispatch_async(|cmd| cmd.set_standby_config(config))
+            .await
+    }
+
+    /// Enter frequency synthesis mode, locking the PLL ahead of a TX or RX operation.
+    pub async fn set_fs(&mut self) -> Result<(), E> {
+        self.recover().await?;
+        self.frontend.set_state(RfState::Fs);
+        self.ll.set_fs().dispatch_async().await
+    }
+
+    /// Return to FS mode instead of STDBY_RC after each TX or RX operation.
+    pub async fn set_auto_fs(&mut self, enable: bool) -> Result<(), E> {
+        self.recover().await?;
+        self.ll
+            .set_auto_fs()
+            .dispatch_async(|cmd| cmd.set_enable(enable))
+            .await?;
+        self.auto_fs = enable;
+        Ok(())
+    }
+
+    /// Read the firmware version to check that the chip is present and responsive.
+    ///
+    /// Enables the APIs that are only available on some variants.
+    pub async fn probe(&mut self, variant: ChipVariant) -> Result<ChipInfo, Error<E>> {
+        self.recover().await.map_err(Error::Spi)?;
+        let firmware = self
+            .ll
+            .firmware_versions()
+            .read_async()
+            .await
+            .map_err(Error::Spi)?
+            .value();
+
+        if let ll::FirmwareVersion::Other(version) = firmware {
+            return Err(Error::UnknownFirmware(version));
+        }
+
+        let chip = ChipInfo { variant, firmware };
+        self.chip = Some(chip);
+        Ok(chip)
+    }
+
+    /// The chip descriptor established by [SX128X::probe], if any.
+    pub fn chip(&self) -> Option<ChipInfo> {
+        self.chip
+    }
+
+    /// Select whether this radio initiates ranging exchanges or responds to them.
+    ///
+    /// Only available on the SX1280.
+    pub async fn set_ranging_role(&mut self, role: ll::RangingRole) -> Result<(), Error<E>> {
+        let chip = self.chip.ok_or(Error::NotProbed)?;
+        if !chip.supports_ranging() {
+            return Err(Error::Unsupported);
+        }
+        self.recover().await.map_err(Error::Spi)?;
+
+        self.ll
+            .set_ranging_role()
+            .dispatch_async(|cmd| cmd.set_value(role))
+            .await
+            .map_err(Error::Spi)
+    }
+
+    /// Only available on the SX1280.
+    pub async fn set_advanced_ranging(&mut self, enable: bool) -> Result<(), Error<E>> {
+        let chip = self.chip.ok_or(Error::NotProbed)?;
+        if !chip.supports_advanced_ranging() {
+            return Err(Error::Unsupported);
+        }
+        self.recover().await.map_err(Error::Spi)?;
+
+        self.ll
+            .set_advanced_ranging()
+            .dispatch_async(|cmd| cmd.set_enable(enable))
+            .await
+            .map_err(Error::Spi)
+    }
+
+    /// Calibrate all blocks.
+    pub async fn calibrate(&mut self) -> Result<(), Error<E>> {
+        self.calibrate_with(CalibrationParams::all()).await
+    }
+
+    /// Calibrate the selected blocks and check the chip status for failure afterwards.
+    ///
+    /// The selection is reused for automatic recalibration.
+    pub async fn calibrate_with(&mut self, params: CalibrationParams) -> Result<(), Error<E>> {
+        self.recover().await.map_err(Error::Spi)?;
+        self.calibration = params;
+        self.calibration_temperature = None;
+
         self.ll
             .calibrate()
             .dispatch_async(|cmd| {
-                cmd.set_rc_64_k_enable(true);
-                cmd.set_rc_13_m_enable_enable(true);
-                cmd.set_pll_enable(true);
-                cmd.set_adc_pulse_enable(true);
-                cmd.set_adc_bulk_n_enable(true);
-                cmd.set_adc_bulk_p_enable(true);
+                cmd.set_rc_64_k_enable(params.rc64k);
+                cmd.set_rc_13_m_enable_enable(params.rc13m);
+                cmd.set_pll_enable(params.pll);
+                cmd.set_adc_pulse_enable(params.adc_pulse);
+                cmd.set_adc_bulk_n_enable(params.adc_bulk_n);
+                cmd.set_adc_bulk_p_enable(params.adc_bulk_p);
             })
             .await
+            .map_err(Error::Spi)?;
+
+        let status = self
+            .ll
+            .get_status()
+            .dispatch_async()
+            .await
+            .map_err(Error::Spi)?;
+        match status.command_status() {
+            Ok(ll::CommandStatus::CommandError | ll::CommandStatus::CommandFailure) => {
+                Err(Error::CalibrationFailed)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Report the current temperature, recalibrating when it drifted too far since the last calibration.
+    ///
+    /// Returns whether a recalibration took place.
+    pub async fn update_temperature(&mut self, celsius: i8) -> Result<bool, Error<E>> {
+        let Some(calibrated) = self.calibration_temperature else {
+            self.calibration_temperature = Some(celsius);
+            return Ok(false);
+        };
+
+        if calibrated.abs_diff(celsius) < RECALIBRATION_THRESHOLD_C {
+            return Ok(false);
+        }
+
+        self.calibrate_with(self.calibration).await?;
+        self.calibration_temperature = Some(celsius);
+        Ok(true)
+    }
+
+    /// Put the chip to sleep, after which only [SX128X::wake] may be used.
+    pub async fn sleep(&mut self, params: SleepParams) -> Result<(), E> {
+        self.recover().await?;
+        self.frontend.set_state(RfState::Sleep);
+        self.ll
+            .set_sleep()
+            .dispatch_async(|cmd| {
+                cmd.set_ram_retention(params.ram_retention);
+                cmd.set_buffer_retention(params.buffer_retention);
+            })
+            .await?;
+        self.sleep = Some(params);
+        if !params.buffer_retention {
+            self.tx_loaded = None;
+        }
+        Ok(())
+    }
+
+    /// Wake the chip from sleep. Harmless if the chip is already awake.
+    ///
+    /// Without RAM retention the chip has lost its configuration, so it is configured and recalibrated.
+    pub async fn wake(&mut self) -> Result<(), Error<E>> {
+        self.ll.interface.wake().await.map_err(Error::Spi)?;
+        self.frontend.set_state(RfState::Standby);
+
+        if let Some(params) = self.sleep.take() {
+            if !params.ram_retention {
+                self.configure().await.map_err(Error::Spi)?;
+                if self.auto_fs {
+                    self.set_auto_fs(true).await.map_err(Error::Spi)?;
+                }
+                self.calibrate_with(self.calibration).await?;
+            }
+        }
+        Ok(())
     }
 
     pub async fn send(&mut self, buf: &[u8]) -> Result<(), E> {
+        self.write_tx(buf).await?;
+        self.transmit().await
+    }
+
+    /// Write a payload to the buffer for [SX128X::transmit_loaded], which can send it repeatedly.
+    ///
+    /// The payload stays loaded until it is overwritten by another one, by a received packet
+    /// (see [buffer::BufferBases]), or is lost in sleep without buffer retention.
+    pub async fn load_tx(&mut self, buf: &[u8]) -> Result<(), Error<E>> {
+        if buf.len() > u8::MAX as usize {
+            return Err(Error::TooLong);
+        }
+        self.write_tx(buf).await.map_err(Error::Spi)
+    }
+
+    /// Write a payload to the TX base and set the payload length to match.
+    async fn write_tx(&mut self, buf: &[u8]) -> Result<(), E> {
+        self.recover().await?;
         self.set_buffer_base_address().await?;
 
         self.params.packet_params.payload_length = buf.len() as u8;
         self.set_packet_params(self.params.packet_params).await?;
 
-        self.ll.buffer().write_all_async(buf).await?;
+        self.ll.interface.write(self.buffer_bases.tx, buf).await?;
+        self.tx_loaded = Some(buf.len() as u8);
+        Ok(())
+    }
+
+    /// Send the payload loaded with [SX128X::load_tx] again, without writing it to the buffer.
+    pub async fn transmit_loaded(&mut self) -> Result<(), Error<E>> {
+        self.recover().await.map_err(Error::Spi)?;
+        let len = self.tx_loaded.ok_or(Error::NothingLoaded)?;
+        self.set_buffer_base_address().await.map_err(Error::Spi)?;
+
+        // Receiving in between changes the payload length.
+        if self.params.packet_params.payload_length != len {
+            self.params.packet_params.payload_length = len;
+            self.set_packet_params(self.params.packet_params)
+                .await
+                .map_err(Error::Spi)?;
+        }
 
+        self.transmit().await.map_err(Error::Spi)
+    }
+
+    /// Transmit the payload at the TX base address.
+    async fn transmit(&mut self) -> Result<(), E> {
         let irq = Irq::TxDone | Irq::RxTxTimeout | Irq::CrcError; // TODO why CRC_ERROR?
 
         self.ll
@@ -152,15 +452,18 @@ impl<
             })
             .await?;
 
+        self.frontend.set_state(RfState::Tx);
+        self.in_flight = true;
         self.ll
             .set_tx()
             .dispatch_async(|cmd| cmd.set_period_base_count(ll::TxTimeoutBaseCount::SingleMode))
             .await?;
 
         let _ = self.dio1.wait_for_high().await;
+        self.frontend.set_state(self.idle_state());
 
         let irqs = self.ll.get_irq_status().dispatch_async().await?;
-        debug!("IRQS {}", irqs);
+        debug!("IRQS {:?}", irqs);
 
         // TODO match IRQs to TxDone
 
@@ -169,7 +472,9 @@ impl<
             .dispatch_async(|cmd| {
                 cmd.set_value(irqs.value());
             })
-            .await
+            .await?;
+        self.in_flight = false;
+        Ok(())
     }
 
     // TODO packet status
@@ -177,67 +482,49 @@ impl<
         &mut self,
         buf: &mut [u8],
     ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
-        self.set_buffer_base_address().await?;
-
-        // TODO mechanism to deal with Irq::PreambleDetected.
-        // TODO mechanism to deal with Irq::HeaderError.
-        let irq = Irq::RxDone | Irq::RxTxTimeout | Irq::HeaderError | Irq::CrcError;
+        self.prepare_rx(buf.len()).await?;
+        self.start_rx().await?;
+        self.finish_rx(buf).await
+    }
 
-        self.ll
-            .set_dio_irq_params()
-            .dispatch_async(|cmd| {
-                cmd.set_irq_mask(irq.bits());
-                cmd.set_dio_1_mask(irq.bits());
-            })
+    /// Receive a packet, giving up with `None` if none arrives within `timeout`.
+    pub async fn receive_within(
+        &mut self,
+        buf: &mut [u8],
+        timeout: RxTimeout,
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        self.prepare_rx(buf.len()).await?;
+        self.start_rx_with(timeout.period_base, timeout.count.into())
             .await?;
+        self.finish_rx(buf).await
+    }
 
-        self.params.packet_params.payload_length = buf.len() as u8;
-        self.set_packet_params(self.params.packet_params).await?;
+    /// Receive continuously, packets of at most `len` bytes, until another operation is started.
+    ///
+    /// Each packet raises DIO1 and is read out with [SX128X::read_packet], the chip stays in RX.
+    pub async fn listen(&mut self, len: usize) -> Result<(), E> {
+        self.prepare_rx(len).await?;
 
+        self.frontend.set_state(RfState::Rx);
+        self.in_flight = true;
         self.ll
             .set_rx()
             .dispatch_async(|cmd| {
                 cmd.set_period_base(ll::RxTimeoutStep::Step15Us625);
-                cmd.set_period_base_count(ll::RxTimeoutBaseCount::SingleMode);
+                cmd.set_period_base_count(ll::RxTimeoutBaseCount::Continuous);
             })
-            .await?;
+            .await
+    }
 
+    /// Wait for the next packet while listening, see [SX128X::listen].
+    ///
+    /// Returns `None` for packets with a CRC or header error.
+    pub async fn read_packet(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
         let _ = self.dio1.wait_for_high().await;
-
-        let irqs = self.ll.get_irq_status().dispatch_async().await?;
-        debug!("IRQS {}", irqs);
-
-        let irqs_value = Irq::from_bits_retain(irqs.value());
-        let rx_done = irqs_value.contains(Irq::RxDone);
-        let crc_error = irqs_value.contains(Irq::CrcError);
-        let result = match (rx_done, crc_error) {
-            (true, false) => {
-                let packet_status = self.ll.get_packet_status().dispatch_async().await?;
-                let rx_buffer_status = self.ll.get_rx_buffer_status().dispatch_async().await?;
-
-                assert_eq!(rx_buffer_status.rx_start_buffer_pointer(), 0);
-
-                let len = rx_buffer_status.rx_payload_length() as usize;
-                let len = core::cmp::min(len, buf.len());
-                self.ll.buffer().read_async(&mut buf[..len]).await?;
-
-                Some((len, packet_status.into()))
-            }
-            (true, true) => {
-                warn!("CRC error on received packet, dropping");
-                None
-            }
-            _ => None,
-        };
-
-        self.ll
-            .clr_irq_status()
-            .dispatch_async(|cmd| {
-                cmd.set_value(irqs.value());
-            })
-            .await?;
-
-        Ok(result)
+        self.take_listened(buf).await
     }
 }
 
@@ -247,13 +534,59 @@ impl<
     DIO: Wait<Error = Infallible>,
     NRESET: OutputPin<Error = Infallible>,
     DELAY: DelayNs,
+    FE: RfFrontend,
     E,
-> SX128X<T, BUSY, DIO, NRESET, DELAY>
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
 {
     async fn set_standbyrc(&mut self) -> Result<(), E> {
+        self.enter_standby(ll::StandbyConfig::StdbyRc).await
+    }
+
+    /// Interrupt any TX or RX operation, returning to standby with the IRQs cleared.
+    async fn abort(&mut self) -> Result<(), E> {
+        self.set_standbyrc().await?;
         self.ll
-            .set_standby()
-            .dispatch_async(|cmd| cmd.set_standby_config(ll::StandbyConfig::StdbyRc))
+            .clr_irq_status()
+            .dispatch_async(|cmd| cmd.set_value(Irq::all().bits()))
+            .await?;
+        self.forget_tx_reachable_by_rx();
+        self.in_flight = false;
+        Ok(())
+    }
+
+    /// Clean up after an operation whose future was dropped before it completed.
+    ///
+    /// Called at the start of every operation, so that cancelling one (e.g. in a `select` with a
+    /// timer) leaves the chip in a known state for the next.
+    async fn recover(&mut self) -> Result<(), E> {
+        if !self.in_flight {
+            return Ok(());
+        }
+        warn!("Previous operation was cancelled, returning to standby");
+        self.abort().await?;
+        // A cancelled receive_with_ack would otherwise answer the next packet.
+        self.ll
+            .set_auto_tx()
+            .dispatch_async(|cmd| cmd.set_time(0))
+            .await?;
+        // A cancelled scan or hop, or a test mode, leaves the chip on another channel.
+        self.set_rf_frequency(self.params.frequency).await?;
+        self.set_tx_params(self.params.tx_params).await
+    }
+
+    /// The state the chip returns to after a TX or RX operation.
+    fn idle_state(&self) -> RfState {
+        if self.auto_fs {
+            RfState::Fs
+        } else {
+            RfState::Standby
+        }
+    }
+
+    async fn set_regulator_mode(&mut self, regulator: RegulatorType) -> Result<(), E> {
+        self.ll
+            .set_regulator_mode()
+            .dispatch_async(|cmd| cmd.set_regulator_type(regulator))
             .await
     }
 
@@ -265,15 +598,175 @@ impl<
     }
 
     async fn set_buffer_base_address(&mut self) -> Result<(), E> {
+        self.set_buffer_base_addresses(self.buffer_bases.tx, self.buffer_bases.rx)
+            .await
+    }
+
+    async fn set_buffer_base_addresses(&mut self, tx_base: u8, rx_base: u8) -> Result<(), E> {
         self.ll
             .set_buffer_base_address()
             .dispatch_async(|cmd| {
-                cmd.set_tx_base_address(0x00);
-                cmd.set_rx_base_address(0x00);
+                cmd.set_tx_base_address(tx_base);
+                cmd.set_rx_base_address(rx_base);
             })
             .await
     }
 
+    /// Set up the buffer, IRQs and packet params for receiving at most `len` bytes.
+    async fn prepare_rx(&mut self, len: usize) -> Result<(), E> {
+        self.recover().await?;
+        self.set_buffer_base_address().await?;
+
+        // TODO mechanism to deal with Irq::PreambleDetected.
+        // TODO mechanism to deal with Irq::HeaderError.
+        let irq = Irq::RxDone | Irq::RxTxTimeout | Irq::HeaderError | Irq::CrcError;
+
+        self.ll
+            .set_dio_irq_params()
+            .dispatch_async(|cmd| {
+                cmd.set_irq_mask(irq.bits());
+                cmd.set_dio_1_mask(irq.bits());
+            })
+            .await?;
+
+        self.params.packet_params.payload_length = len as u8;
+        self.set_packet_params(self.params.packet_params).await
+    }
+
+    /// Forget the loaded TX payload if `len` bytes written at `offset` overlap it.
+    fn forget_overwritten_tx(&mut self, offset: u8, len: usize) {
+        let Some(loaded) = self.tx_loaded else {
+            return;
+        };
+        let tx = self.buffer_bases.tx;
+        // Both ranges wrap around the end of the buffer.
+        if (offset.wrapping_sub(tx) as usize) < loaded as usize
+            || (len > 0 && (tx.wrapping_sub(offset) as usize) < len)
+        {
+            self.tx_loaded = None;
+        }
+    }
+
+    /// Forget the loaded payload if an RX of unknown length may have reached it, up to 255 bytes.
+    fn forget_tx_reachable_by_rx(&mut self) {
+        self.forget_overwritten_tx(self.buffer_bases.rx, 0xFF);
+    }
+
+    /// Enter RX for a single packet.
+    async fn start_rx(&mut self) -> Result<(), E> {
+        self.start_rx_with(
+            ll::RxTimeoutStep::Step15Us625,
+            ll::RxTimeoutBaseCount::SingleMode,
+        )
+        .await
+    }
+
+    /// Enter RX, ending after `count` steps of `period_base`.
+    async fn start_rx_with(
+        &mut self,
+        period_base: ll::RxTimeoutStep,
+        count: ll::RxTimeoutBaseCount,
+    ) -> Result<(), E> {
+        self.frontend.set_state(RfState::Rx);
+        self.in_flight = true;
+        self.ll
+            .set_rx()
+            .dispatch_async(|cmd| {
+                cmd.set_period_base(period_base);
+                cmd.set_period_base_count(count);
+            })
+            .await
+    }
+
+    /// Wait for the RX operation to end and read out the packet.
+    async fn finish_rx(&mut self, buf: &mut [u8]) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        let _ = self.dio1.wait_for_high().await;
+        self.frontend.set_state(self.idle_state());
+        self.take_packet(buf).await
+    }
+
+    /// Read out the packet signalled by DIO1 and clear the IRQs, ending the operation.
+    async fn take_packet(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        let result = self.take_listened(buf).await?;
+        self.in_flight = false;
+        Ok(result)
+    }
+
+    /// Read out the packet signalled by DIO1 and clear the IRQs, the chip may still be receiving.
+    async fn take_listened(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        let irqs = self.ll.get_irq_status().dispatch_async().await?;
+        debug!("IRQS {:?}", irqs);
+
+        let result = self
+            .read_received(Irq::from_bits_retain(irqs.value()), buf)
+            .await?;
+
+        self.ll
+            .clr_irq_status()
+            .dispatch_async(|cmd| {
+                cmd.set_value(irqs.value());
+            })
+            .await?;
+
+        Ok(result)
+    }
+
+    /// Read out the received packet, if the IRQs signal a valid one.
+    async fn read_received(
+        &mut self,
+        irqs: Irq,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        let Some(received) = self.locate_received(irqs).await? else {
+            return Ok(None);
+        };
+
+        let len = core::cmp::min(received.len, buf.len());
+        self.ll
+            .interface
+            .read(received.offset, &mut buf[..len])
+            .await?;
+
+        Ok(Some((len, received.status)))
+    }
+
+    /// Find the received packet in the buffer, if the IRQs signal a valid one.
+    async fn locate_received(&mut self, irqs: Irq) -> Result<Option<Received>, E> {
+        let rx_done = irqs.contains(Irq::RxDone);
+        let crc_error = irqs.contains(Irq::CrcError);
+        match (rx_done, crc_error) {
+            (true, false) => {
+                let packet_status = self.ll.get_packet_status().dispatch_async().await?;
+                let rx_buffer_status = self.ll.get_rx_buffer_status().dispatch_async().await?;
+
+                let received = Received {
+                    offset: rx_buffer_status.rx_start_buffer_pointer(),
+                    len: rx_buffer_status.rx_payload_length() as usize,
+                    status: packet_status.into(),
+                };
+                self.forget_overwritten_tx(received.offset, received.len);
+                Ok(Some(received))
+            }
+            (true, true) => {
+                warn!("CRC error on received packet, dropping");
+                // The corrupted packet was still written to the buffer.
+                let rx_buffer_status = self.ll.get_rx_buffer_status().dispatch_async().await?;
+                self.forget_overwritten_tx(
+                    rx_buffer_status.rx_start_buffer_pointer(),
+                    rx_buffer_status.rx_payload_length() as usize,
+                );
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
     async fn set_modulation_params(
         &mut self,
         modulation_params: LoRaModulationParams,
@@ -332,10 +825,7 @@ impl<
     }
 
     async fn set_tx_params(&mut self, tx_params: TxParams) -> Result<(), E> {
-        let power = tx_params.power;
-        let power = core::cmp::max(power, -18);
-        let power = core::cmp::min(power, 13);
-        let power_reg = (power + 18) as u8;
+        let power_reg = self.frontend.tx_power_register(tx_params.power);
 
         self.ll
             .set_tx_params()
@@ -0,0 +1,167 @@
+use core::convert::Infallible;
+
+use device_driver::AsyncBufferInterface;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    Error, SX128X,
+    buffer::Received,
+    frontend::{RfFrontend, RfState},
+    irq::Irq,
+    lora::{LoRaHeader, LoRaPacketStatus},
+};
+
+/// Buffer offset of the acknowledgement, the received packet is stored below it.
+pub const ACK_BASE_ADDRESS: u8 = 0x80;
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    /// Receive a packet and have the radio reply with `ack` by itself, `delay_us` + 33 us after RxDone.
+    ///
+    /// The radio sends the acknowledgement after every RxDone, including packets with a CRC error.
+    /// Received packets must fit below [ACK_BASE_ADDRESS], so `ack` is limited to 128 bytes.
+    /// In implicit header mode both directions use the length of `buf`, which must not exceed
+    /// [ACK_BASE_ADDRESS], and `ack` is padded with zeros to it.
+    /// A longer packet in explicit header mode overwrites the acknowledgement before it is sent,
+    /// it is reported as [Error::TooLong] once the radio is done.
+    /// When using an external front-end, `delay_us` must cover the time for the host to switch it to TX.
+    pub async fn receive_with_ack(
+        &mut self,
+        buf: &mut [u8],
+        ack: &[u8],
+        delay_us: u16,
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, Error<E>> {
+        let payload_length = match self.params.packet_params.header_type {
+            LoRaHeader::Explicit => ack.len(),
+            LoRaHeader::Implicit => buf.len(),
+        };
+        if ack.len() > payload_length || payload_length > ACK_BASE_ADDRESS as usize {
+            return Err(Error::TooLong);
+        }
+        self.recover().await.map_err(Error::Spi)?;
+
+        self.set_buffer_base_addresses(ACK_BASE_ADDRESS, 0x00)
+            .await
+            .map_err(Error::Spi)?;
+        self.ll
+            .interface
+            .write(ACK_BASE_ADDRESS, ack)
+            .await
+            .map_err(Error::Spi)?;
+        let mut offset = ACK_BASE_ADDRESS as usize + ack.len();
+        while offset < ACK_BASE_ADDRESS as usize + payload_length {
+            let padding = [0u8; 16];
+            let len = core::cmp::min(
+                padding.len(),
+                ACK_BASE_ADDRESS as usize + payload_length - offset,
+            );
+            self.ll
+                .interface
+                .write(offset as u8, &padding[..len])
+                .await
+                .map_err(Error::Spi)?;
+            offset += len;
+        }
+        self.forget_overwritten_tx(ACK_BASE_ADDRESS, payload_length);
+
+        let Some(received) = self
+            .exchange_with_ack(payload_length as u8, delay_us)
+            .await
+            .map_err(Error::Spi)?
+        else {
+            return Ok(None);
+        };
+        if received.len > ACK_BASE_ADDRESS as usize {
+            warn!("Received packet overwrote the acknowledgement");
+            return Err(Error::TooLong);
+        }
+
+        let len = core::cmp::min(received.len, buf.len());
+        self.ll
+            .interface
+            .read(received.offset, &mut buf[..len])
+            .await
+            .map_err(Error::Spi)?;
+
+        Ok(Some((len, received.status)))
+    }
+
+    /// Receive with auto TX armed and wait for the acknowledgement, the packet stays in the buffer.
+    async fn exchange_with_ack(
+        &mut self,
+        payload_length: u8,
+        delay_us: u16,
+    ) -> Result<Option<Received>, E> {
+        let irq = Irq::RxDone | Irq::TxDone | Irq::RxTxTimeout | Irq::HeaderError | Irq::CrcError;
+
+        self.ll
+            .set_dio_irq_params()
+            .dispatch_async(|cmd| {
+                cmd.set_irq_mask(irq.bits());
+                cmd.set_dio_1_mask(irq.bits());
+            })
+            .await?;
+
+        // In explicit header mode the payload length only applies to the transmitted acknowledgement.
+        self.params.packet_params.payload_length = payload_length;
+        self.set_packet_params(self.params.packet_params).await?;
+
+        self.ll
+            .set_auto_tx()
+            .dispatch_async(|cmd| cmd.set_time(delay_us))
+            .await?;
+
+        self.start_rx().await?;
+
+        let _ = self.dio1.wait_for_high().await;
+
+        let irqs = self.ll.get_irq_status().dispatch_async().await?;
+        debug!("IRQS {:?}", irqs);
+
+        let irqs_value = Irq::from_bits_retain(irqs.value());
+        let result = if irqs_value.contains(Irq::RxDone) {
+            self.frontend.set_state(RfState::Tx);
+            let result = self.locate_received(irqs_value).await?;
+
+            self.ll
+                .clr_irq_status()
+                .dispatch_async(|cmd| cmd.set_value(irqs.value()))
+                .await?;
+
+            // Wait for the acknowledgement to be sent.
+            let _ = self.dio1.wait_for_high().await;
+            let irqs = self.ll.get_irq_status().dispatch_async().await?;
+            debug!("IRQS {:?}", irqs);
+            self.ll
+                .clr_irq_status()
+                .dispatch_async(|cmd| cmd.set_value(irqs.value()))
+                .await?;
+
+            result
+        } else {
+            self.ll
+                .clr_irq_status()
+                .dispatch_async(|cmd| cmd.set_value(irqs.value()))
+                .await?;
+            None
+        };
+        self.frontend.set_state(self.idle_state());
+
+        self.ll
+            .set_auto_tx()
+            .dispatch_async(|cmd| cmd.set_time(0))
+            .await?;
+        self.in_flight = false;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,103 @@
+use core::convert::Infallible;
+
+use device_driver::AsyncBufferInterface;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{SX128X, frontend::RfFrontend, irq::Irq, lora::LoRaPacketStatus};
+
+/// Where TX payloads are written and RX payloads stored in the 256 byte data buffer.
+///
+/// With the default of 0 for both, a transmission overwrites the last received packet. Separate
+/// bases, e.g. `tx: 0x80, rx: 0x00`, keep both as long as they fit in their half.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BufferBases {
+    pub tx: u8,
+    pub rx: u8,
+}
+
+/// A packet left in the data buffer by [SX128X::receive_in_place].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Received {
+    /// Buffer offset of the first byte of the payload.
+    pub offset: u8,
+    pub len: usize,
+    pub status: LoRaPacketStatus,
+}
+
+impl Received {
+    /// Buffer offset of byte `index` of the payload, for reading it with [SX128X::read_buffer].
+    ///
+    /// The buffer wraps around, so the payload may continue at offset 0.
+    pub fn offset_of(&self, index: usize) -> u8 {
+        self.offset.wrapping_add(index as u8)
+    }
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    pub fn buffer_bases(&self) -> BufferBases {
+        self.buffer_bases
+    }
+
+    /// Use other buffer base addresses from the next TX or RX operation on.
+    pub fn set_buffer_bases(&mut self, bases: BufferBases) {
+        if bases.tx != self.buffer_bases.tx {
+            self.tx_loaded = None;
+        }
+        self.buffer_bases = bases;
+    }
+
+    /// Read `buf.len()` bytes of the data buffer from `offset` on.
+    pub async fn read_buffer(&mut self, offset: u8, buf: &mut [u8]) -> Result<(), E> {
+        self.ll.interface.read(offset, buf).await?;
+        Ok(())
+    }
+
+    /// Write `buf` to the data buffer from `offset` on.
+    ///
+    /// Overwriting a payload loaded with [SX128X::load_tx] unloads it.
+    pub async fn write_buffer(&mut self, offset: u8, buf: &[u8]) -> Result<(), E> {
+        self.ll.interface.write(offset, buf).await?;
+        self.forget_overwritten_tx(offset, buf.len());
+        Ok(())
+    }
+
+    /// Receive a packet of at most `len` bytes and leave it in the data buffer.
+    ///
+    /// Read it with [SX128X::read_buffer] and [Received::offset_of], e.g. the header first and the
+    /// body into its final destination once the header is parsed. The packet stays in the buffer
+    /// until the next packet is received, or a transmission overwrites it, see [BufferBases].
+    pub async fn receive_in_place(&mut self, len: usize) -> Result<Option<Received>, E> {
+        self.prepare_rx(len).await?;
+        self.start_rx().await?;
+
+        let _ = self.dio1.wait_for_high().await;
+        self.frontend.set_state(self.idle_state());
+
+        let irqs = self.ll.get_irq_status().dispatch_async().await?;
+        debug!("IRQS {:?}", irqs);
+
+        let received = self
+            .locate_received(Irq::from_bits_retain(irqs.value()))
+            .await?;
+
+        self.ll
+            .clr_irq_status()
+            .dispatch_async(|cmd| cmd.set_value(irqs.value()))
+            .await?;
+        self.in_flight = false;
+
+        Ok(received)
+    }
+}
@@ -0,0 +1,86 @@
+/// Temperature change in °C after which [crate::hl::SX128X::update_temperature] recalibrates.
+pub const RECALIBRATION_THRESHOLD_C: u8 = 20;
+
+/// Selection of the blocks to calibrate with `CALIBRATE`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CalibrationParams {
+    pub rc64k: bool,
+    pub rc13m: bool,
+    pub pll: bool,
+    pub adc_pulse: bool,
+    pub adc_bulk_n: bool,
+    pub adc_bulk_p: bool,
+}
+
+impl CalibrationParams {
+    /// Calibrate every block.
+    pub const fn all() -> Self {
+        Self {
+            rc64k: true,
+            rc13m: true,
+            pll: true,
+            adc_pulse: true,
+            adc_bulk_n: true,
+            adc_bulk_p: true,
+        }
+    }
+
+    /// Calibrate no block, to be extended with the builder methods.
+    pub const fn none() -> Self {
+        Self {
+            rc64k: false,
+            rc13m: false,
+            pll: false,
+            adc_pulse: false,
+            adc_bulk_n: false,
+            adc_bulk_p: false,
+        }
+    }
+
+    pub const fn rc64k(mut self, enable: bool) -> Self {
+        self.rc64k = enable;
+        self
+    }
+
+    pub const fn rc13m(mut self, enable: bool) -> Self {
+        self.rc13m = enable;
+        self
+    }
+
+    pub const fn pll(mut self, enable: bool) -> Self {
+        self.pll = enable;
+        self
+    }
+
+    pub const fn adc_pulse(mut self, enable: bool) -> Self {
+        self.adc_pulse = enable;
+        self
+    }
+
+    pub const fn adc_bulk_n(mut self, enable: bool) -> Self {
+        self.adc_bulk_n = enable;
+        self
+    }
+
+    pub const fn adc_bulk_p(mut self, enable: bool) -> Self {
+        self.adc_bulk_p = enable;
+        self
+    }
+}
+
+impl Default for CalibrationParams {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// What to keep while in sleep mode, see [crate::hl::SX128X::sleep].
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SleepParams {
+    /// Keep the data RAM, holding the context saved with `SET_SAVE_CONTEXT`.
+    pub ram_retention: bool,
+    /// Keep the contents of the data buffer.
+    pub buffer_retention: bool,
+}
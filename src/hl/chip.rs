@@ -0,0 +1,30 @@
+use crate::ll::FirmwareVersion;
+
+/// Member of the SX128x family.
+///
+/// The variant cannot be read back from the chip, so it has to be supplied by the board.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChipVariant {
+    Sx1280,
+    /// Identical to the SX1280, but without the ranging engine.
+    Sx1281,
+}
+
+/// Chip descriptor, as established by [crate::hl::SX128X::probe].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChipInfo {
+    pub variant: ChipVariant,
+    pub firmware: FirmwareVersion,
+}
+
+impl ChipInfo {
+    pub fn supports_ranging(&self) -> bool {
+        self.variant == ChipVariant::Sx1280
+    }
+
+    pub fn supports_advanced_ranging(&self) -> bool {
+        self.supports_ranging()
+    }
+}
@@ -0,0 +1,69 @@
+use core::convert::Infallible;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    SX128X, buffer::BufferBases, calibration::CalibrationParams, chip::ChipInfo,
+    frontend::RfFrontend, lora::LoRaModemParams,
+};
+
+/// Host-side snapshot of the radio configuration.
+///
+/// Keep it in memory that survives a host reset to resume control over a radio that retained its
+/// configuration, without running [SX128X::configure] again.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RadioContext {
+    pub params: LoRaModemParams,
+    pub chip: Option<ChipInfo>,
+    pub calibration: CalibrationParams,
+    pub auto_fs: bool,
+    pub buffer_bases: BufferBases,
+    /// Length of the payload loaded with [SX128X::load_tx] at the TX base, if still intact.
+    pub tx_loaded: Option<u8>,
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    /// Snapshot of the current configuration, without touching the chip.
+    pub fn context(&self) -> RadioContext {
+        RadioContext {
+            params: self.params,
+            chip: self.chip,
+            calibration: self.calibration,
+            auto_fs: self.auto_fs,
+            buffer_bases: self.buffer_bases,
+            tx_loaded: self.tx_loaded,
+        }
+    }
+
+    /// Save the chip configuration to its data RAM and return the matching snapshot.
+    ///
+    /// The chip restores its configuration by itself when waking from sleep with RAM retention.
+    pub async fn save_context(&mut self) -> Result<RadioContext, E> {
+        self.recover().await?;
+        self.ll.set_save_context().dispatch_async().await?;
+        Ok(self.context())
+    }
+
+    /// Adopt a snapshot taken with [SX128X::save_context], without touching the chip.
+    ///
+    /// Follow up with [SX128X::wake] if the chip may still be asleep.
+    pub fn restore_context(&mut self, context: RadioContext) {
+        self.params = context.params;
+        self.chip = context.chip;
+        self.calibration = context.calibration;
+        self.auto_fs = context.auto_fs;
+        self.buffer_bases = context.buffer_bases;
+        self.tx_loaded = context.tx_loaded;
+    }
+}
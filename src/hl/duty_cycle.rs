@@ -0,0 +1,67 @@
+use core::convert::Infallible;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    SX128X,
+    frontend::{RfFrontend, RfState},
+    lora::LoRaPacketStatus,
+};
+use crate::ll;
+
+/// Timing of duty-cycled reception, both periods are expressed in steps of `period_base`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DutyCycleParams {
+    pub period_base: ll::RxTimeoutStep,
+    pub rx_count: u16,
+    pub sleep_count: u16,
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    /// Keep receiving while a preamble is detected, even if the RX period has elapsed.
+    ///
+    /// Combined with [SX128X::receive_duty_cycled] this enables wake-on-radio, where transmitters
+    /// send preambles longer than the sleep period of the receiver.
+    pub async fn set_long_preamble(&mut self, enable: bool) -> Result<(), E> {
+        self.recover().await?;
+        self.ll
+            .set_long_preamble()
+            .dispatch_async(|cmd| cmd.set_enable(enable))
+            .await
+    }
+
+    /// Receive a packet, alternating between listening and sleeping until one arrives.
+    ///
+    /// The front-end is kept in RX throughout.
+    pub async fn receive_duty_cycled(
+        &mut self,
+        buf: &mut [u8],
+        params: DutyCycleParams,
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        self.prepare_rx(buf.len()).await?;
+
+        self.frontend.set_state(RfState::Rx);
+        self.in_flight = true;
+        self.ll
+            .set_rx_duty_cycle()
+            .dispatch_async(|cmd| {
+                cmd.set_period_base(params.period_base);
+                cmd.set_rx_period_base_count(params.rx_count);
+                cmd.set_sleep_period_base_count(params.sleep_count);
+            })
+            .await?;
+
+        self.finish_rx(buf).await
+    }
+}
@@ -0,0 +1,126 @@
+const CRYSTAL_FREQ_HZ: u64 = 52_000_000u64;
+const PLL_STEPS: u64 = 2u64.pow(18);
+
+/// RF frequency, stored as the 24-bit PLL step count used by `SET_RF_FREQUENCY`.
+///
+/// One PLL step is 52 MHz / 2^18, roughly 198.4 Hz.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frequency {
+    raw: [u8; 3],
+}
+
+impl Frequency {
+    /// Lower edge of the band supported by the SX128x.
+    pub const MIN_HZ: u64 = 2_400_000_000;
+    /// Upper edge of the band supported by the SX128x.
+    pub const MAX_HZ: u64 = 2_500_000_000;
+
+    /// Convert a frequency in Hz to PLL steps, rounding down.
+    ///
+    /// Performs no range checks, see [Frequency::checked_new].
+    pub const fn new(freq_hz: u64) -> Self {
+        let freq_steps = ((freq_hz * PLL_STEPS) / CRYSTAL_FREQ_HZ) as u32;
+        let array = freq_steps.to_be_bytes();
+
+        Self {
+            raw: [array[1], array[2], array[3]],
+        }
+    }
+
+    /// Convert a frequency in Hz to PLL steps, if it lies within the SX128x band.
+    pub const fn checked_new(freq_hz: u64) -> Option<Self> {
+        if freq_hz < Self::MIN_HZ || freq_hz > Self::MAX_HZ {
+            return None;
+        }
+        Some(Self::new(freq_hz))
+    }
+
+    pub const fn from_bytes(raw: [u8; 3]) -> Self {
+        Self { raw }
+    }
+
+    pub fn as_bytes(&self) -> [u8; 3] {
+        self.raw
+    }
+
+    /// The frequency in Hz.
+    ///
+    /// Rounds up to the smallest frequency that maps onto the same PLL setting,
+    /// such that `Frequency::new(f.to_hz()) == f`.
+    pub const fn to_hz(&self) -> u64 {
+        let steps = u32::from_be_bytes([0, self.raw[0], self.raw[1], self.raw[2]]) as u64;
+        (steps * CRYSTAL_FREQ_HZ).div_ceil(PLL_STEPS)
+    }
+
+    /// Shift the frequency by `offset_hz`, if the result lies within the SX128x band.
+    pub const fn checked_offset(&self, offset_hz: i64) -> Option<Self> {
+        match (self.to_hz() as i64).checked_add(offset_hz) {
+            Some(freq_hz) if freq_hz >= 0 => Self::checked_new(freq_hz as u64),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Frequency {
+    fn default() -> Self {
+        Self::new(2_440_000_000)
+    }
+}
+
+/// A set of equally spaced channels, addressable by index.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelPlan {
+    start: Frequency,
+    spacing_hz: u32,
+    count: u16,
+}
+
+impl ChannelPlan {
+    /// Create a plan of `count` channels, starting at `start` and `spacing_hz` apart.
+    ///
+    /// Returns `None` if the plan is empty or any of its channels lies outside of the SX128x band.
+    pub const fn new(start: Frequency, spacing_hz: u32, count: u16) -> Option<Self> {
+        if count == 0 {
+            return None;
+        }
+
+        let span_hz = spacing_hz as i64 * (count as i64 - 1);
+        match start.checked_offset(span_hz) {
+            Some(_) => Some(Self {
+                start,
+                spacing_hz,
+                count,
+            }),
+            None => None,
+        }
+    }
+
+    pub const fn start(&self) -> Frequency {
+        self.start
+    }
+
+    pub const fn spacing_hz(&self) -> u32 {
+        self.spacing_hz
+    }
+
+    /// The number of channels in this plan.
+    pub const fn count(&self) -> u16 {
+        self.count
+    }
+
+    /// The frequency of the channel at `index`, or `None` if the index is out of range.
+    pub const fn channel(&self, index: u16) -> Option<Frequency> {
+        if index >= self.count {
+            return None;
+        }
+        self.start
+            .checked_offset(self.spacing_hz as i64 * index as i64)
+    }
+
+    /// Iterate over all channels in order of their index.
+    pub fn channels(&self) -> impl Iterator<Item = Frequency> + '_ {
+        (0..self.count).filter_map(|index| self.channel(index))
+    }
+}
@@ -0,0 +1,92 @@
+use core::convert::Infallible;
+
+use embedded_hal::digital::OutputPin;
+
+/// Operating state of the radio, as seen by the RF front-end.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RfState {
+    Sleep,
+    Standby,
+    Fs,
+    Tx,
+    Rx,
+}
+
+/// Board-specific circuitry between the SX128x and the antenna, like external PAs, LNAs and RF switches.
+pub trait RfFrontend {
+    /// Called right before the radio enters `state`.
+    fn set_state(&mut self, state: RfState);
+
+    /// Translate the requested output power in dBm to a `SET_TX_PARAMS` power register value.
+    ///
+    /// By default the chip output is used as-is, clamped to its range of -18 to 13 dBm.
+    fn tx_power_register(&self, power: i8) -> u8 {
+        let power = core::cmp::max(power, -18);
+        let power = core::cmp::min(power, 13);
+        (power + 18) as u8
+    }
+}
+
+/// Front-end for boards where the SX128x is directly connected to the antenna.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NoFrontend;
+
+impl RfFrontend for NoFrontend {
+    fn set_state(&mut self, _state: RfState) {}
+}
+
+/// Front-end with a TX-enable and RX-enable pin, like found on modules with an external PA and LNA.
+///
+/// The power table maps the module output power in dBm to the power register value yielding it.
+pub struct SwitchedFrontend<TXEN, RXEN>
+where
+    TXEN: OutputPin<Error = Infallible>,
+    RXEN: OutputPin<Error = Infallible>,
+{
+    tx_enable: TXEN,
+    rx_enable: RXEN,
+    power_table: &'static [(i8, u8)],
+}
+
+impl<TXEN, RXEN> SwitchedFrontend<TXEN, RXEN>
+where
+    TXEN: OutputPin<Error = Infallible>,
+    RXEN: OutputPin<Error = Infallible>,
+{
+    /// The power table must be sorted by ascending output power.
+    pub fn new(tx_enable: TXEN, rx_enable: RXEN, power_table: &'static [(i8, u8)]) -> Self {
+        Self {
+            tx_enable,
+            rx_enable,
+            power_table,
+        }
+    }
+
+    pub fn take(self) -> (TXEN, RXEN) {
+        (self.tx_enable, self.rx_enable)
+    }
+}
+
+impl<TXEN, RXEN> RfFrontend for SwitchedFrontend<TXEN, RXEN>
+where
+    TXEN: OutputPin<Error = Infallible>,
+    RXEN: OutputPin<Error = Infallible>,
+{
+    fn set_state(&mut self, state: RfState) {
+        let _ = self.tx_enable.set_state((state == RfState::Tx).into());
+        let _ = self.rx_enable.set_state((state == RfState::Rx).into());
+    }
+
+    /// Picks the highest table entry not exceeding `power`, or the lowest entry if there is none.
+    fn tx_power_register(&self, power: i8) -> u8 {
+        self.power_table
+            .iter()
+            .rev()
+            .find(|(output, _)| *output <= power)
+            .or(self.power_table.first())
+            .map(|(_, reg)| *reg)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,95 @@
+use core::convert::Infallible;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{SX128X, frontend::RfFrontend};
+use crate::ll::{self, GainValue};
+
+/// LNA gain regime.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LnaMode {
+    /// Reset default.
+    #[default]
+    LowPower,
+    /// Improves sensitivity by about 3 dB, at the cost of a higher current consumption.
+    HighSensitivity,
+}
+
+impl From<LnaMode> for ll::RxGain {
+    fn from(mode: LnaMode) -> Self {
+        match mode {
+            LnaMode::LowPower => ll::RxGain::LowPower,
+            LnaMode::HighSensitivity => ll::RxGain::HighSensitivity,
+        }
+    }
+}
+
+/// How the receiver gain is controlled.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GainControl {
+    /// Automatic gain control, the reset default.
+    #[default]
+    Automatic,
+    /// Fixed gain step, e.g. to avoid saturating the front-end at short range.
+    Manual(GainValue),
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RxGainParams {
+    pub lna_mode: LnaMode,
+    pub gain_control: GainControl,
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    pub async fn set_rx_gain(&mut self, params: RxGainParams) -> Result<(), E> {
+        self.recover().await?;
+        self.ll
+            .rx_gain()
+            .modify_async(|reg| reg.set_mode(params.lna_mode.into()))
+            .await?;
+
+        match params.gain_control {
+            GainControl::Automatic => self.restore_agc().await,
+            GainControl::Manual(gain) => {
+                self.ll
+                    .manual_gain_setting()
+                    .modify_async(|reg| reg.set_gain_control(ll::GainControl::Manual))
+                    .await?;
+                self.ll
+                    .lna_gain_value()
+                    .modify_async(|reg| reg.set_setting(gain))
+                    .await?;
+                self.ll
+                    .lna_gain_control()
+                    .modify_async(|reg| reg.set_gain_control(ll::LnaGainControl::Manual))
+                    .await
+            }
+        }
+    }
+
+    /// Hand control of the receiver gain back to the AGC.
+    pub async fn restore_agc(&mut self) -> Result<(), E> {
+        self.recover().await?;
+        self.ll
+            .lna_gain_control()
+            .modify_async(|reg| reg.set_gain_control(ll::LnaGainControl::Automatic))
+            .await?;
+        self.ll
+            .manual_gain_setting()
+            .modify_async(|reg| reg.set_gain_control(ll::GainControl::Automatic))
+            .await
+    }
+}
@@ -0,0 +1,228 @@
+use core::convert::Infallible;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    ChannelPlan, Frequency, RxTimeout, SX128X, frontend::RfFrontend, lora::LoRaPacketStatus,
+};
+
+const FEISTEL_ROUNDS: u32 = 4;
+
+/// Pseudo-random channel order derived from a shared seed.
+///
+/// Every channel of the plan is visited exactly once per period of [ChannelPlan::count] hops,
+/// after which the sequence repeats. Both ends derive the same sequence from the same plan and seed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HopSequence {
+    plan: ChannelPlan,
+    seed: u32,
+    half_bits: u32,
+}
+
+impl HopSequence {
+    pub fn new(plan: ChannelPlan, seed: u32) -> Self {
+        let bits = u16::BITS - (plan.count() - 1).leading_zeros();
+        Self {
+            plan,
+            seed,
+            half_bits: bits.div_ceil(2).max(1),
+        }
+    }
+
+    pub fn plan(&self) -> &ChannelPlan {
+        &self.plan
+    }
+
+    /// The number of hops after which the sequence repeats.
+    pub fn period(&self) -> u16 {
+        self.plan.count()
+    }
+
+    /// The channel index used at `position` in the sequence.
+    pub fn channel_index(&self, position: u16) -> u16 {
+        // Cycle-walk the permutation over the power-of-two domain until we land on a valid channel.
+        let mut value = (position % self.period()) as u32;
+        loop {
+            value = self.permute(value);
+            if value < self.period() as u32 {
+                return value as u16;
+            }
+        }
+    }
+
+    /// The channel frequency used at `position` in the sequence.
+    pub fn channel(&self, position: u16) -> Frequency {
+        // The plan guarantees that every index within its count is a valid frequency.
+        unwrap!(self.plan.channel(self.channel_index(position)))
+    }
+
+    /// The position in the sequence at which `channel_index` is used.
+    pub fn position_of(&self, channel_index: u16) -> u16 {
+        let mut value = (channel_index % self.period()) as u32;
+        loop {
+            value = self.unpermute(value);
+            if value < self.period() as u32 {
+                return value as u16;
+            }
+        }
+    }
+
+    fn mask(&self) -> u32 {
+        (1 << self.half_bits) - 1
+    }
+
+    fn round(&self, round: u32, value: u32) -> u32 {
+        let mut x = value ^ self.seed ^ round.wrapping_mul(0x9E37_79B9);
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x7FEB_352D);
+        x ^= x >> 15;
+        x = x.wrapping_mul(0x846C_A68B);
+        x ^= x >> 16;
+        x & self.mask()
+    }
+
+    fn permute(&self, value: u32) -> u32 {
+        let (mut left, mut right) = (value >> self.half_bits, value & self.mask());
+        for round in 0..FEISTEL_ROUNDS {
+            (left, right) = (right, left ^ self.round(round, right));
+        }
+        (left << self.half_bits) | right
+    }
+
+    fn unpermute(&self, value: u32) -> u32 {
+        let (mut left, mut right) = (value >> self.half_bits, value & self.mask());
+        for round in (0..FEISTEL_ROUNDS).rev() {
+            (left, right) = (right ^ self.round(round, left), left);
+        }
+        (left << self.half_bits) | right
+    }
+}
+
+/// When a [Hopper] moves on to the next channel.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HopMode {
+    /// Hop after every transmitted or received packet.
+    #[default]
+    PerPacket,
+    /// Stay on a channel until [Hopper::hop] is called, typically from a dwell timer.
+    Dwell,
+}
+
+/// Tracks the current position in a [HopSequence].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hopper {
+    sequence: HopSequence,
+    mode: HopMode,
+    position: u16,
+}
+
+impl Hopper {
+    pub fn new(sequence: HopSequence, mode: HopMode) -> Self {
+        Self {
+            sequence,
+            mode,
+            position: 0,
+        }
+    }
+
+    pub fn sequence(&self) -> &HopSequence {
+        &self.sequence
+    }
+
+    pub fn mode(&self) -> HopMode {
+        self.mode
+    }
+
+    pub fn position(&self) -> u16 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: u16) {
+        self.position = position % self.sequence.period();
+    }
+
+    /// The frequency of the current hop.
+    pub fn channel(&self) -> Frequency {
+        self.sequence.channel(self.position)
+    }
+
+    /// Move on to the next channel in the sequence.
+    pub fn hop(&mut self) {
+        self.position = (self.position + 1) % self.sequence.period();
+    }
+
+    /// Jump to the position at which `channel_index` is used.
+    pub fn park(&mut self, channel_index: u16) {
+        self.position = self.sequence.position_of(channel_index);
+    }
+
+    fn packet_done(&mut self) {
+        if self.mode == HopMode::PerPacket {
+            self.hop();
+        }
+    }
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    /// Send a packet on the current channel of `hopper`.
+    pub async fn send_hopping(&mut self, hopper: &mut Hopper, buf: &[u8]) -> Result<(), E> {
+        self.set_frequency(hopper.channel()).await?;
+        self.send(buf).await?;
+        hopper.packet_done();
+        Ok(())
+    }
+
+    /// Receive a packet on the current channel of `hopper`, waiting at most `timeout` if given.
+    ///
+    /// In [HopMode::PerPacket] the hopper also moves on if the packet was dropped or timed out,
+    /// as the transmitter will have hopped regardless.
+    pub async fn receive_hopping(
+        &mut self,
+        hopper: &mut Hopper,
+        buf: &mut [u8],
+        timeout: Option<RxTimeout>,
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        self.set_frequency(hopper.channel()).await?;
+        let result = match timeout {
+            Some(timeout) => self.receive_within(buf, timeout).await?,
+            None => self.receive(buf).await?,
+        };
+        hopper.packet_done();
+        Ok(result)
+    }
+
+    /// Regain a lost sequence by parking on `channel_index` and listening for up to `timeout`.
+    ///
+    /// On a valid packet `hopper` is aligned with the transmitter, in [HopMode::Dwell] the caller
+    /// should restart its dwell timer. Otherwise `None` is returned with `hopper` still parked,
+    /// call again to keep listening.
+    pub async fn resync_hopping(
+        &mut self,
+        hopper: &mut Hopper,
+        channel_index: u16,
+        buf: &mut [u8],
+        timeout: RxTimeout,
+    ) -> Result<Option<(usize, LoRaPacketStatus)>, E> {
+        hopper.park(channel_index);
+        self.set_frequency(hopper.channel()).await?;
+
+        let result = self.receive_within(buf, timeout).await?;
+        if result.is_some() {
+            hopper.packet_done();
+        }
+        Ok(result)
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Frequency, TxParams};
+use super::{Frequency, RegulatorType, TxParams};
 use crate::ll::field_sets::GetPacketStatusFieldsOut;
 
 #[derive(Copy, Clone, Default, PartialEq, Debug)]
@@ -130,6 +130,8 @@ impl LoRaPacketParams {
 #[derive(Copy, Clone, Default, PartialEq, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct LoRaModemParams {
+    /// Use [RegulatorType::DcDc] when the board has the DC-DC inductor fitted, to save power.
+    pub regulator: RegulatorType,
     pub frequency: Frequency,
     pub tx_params: TxParams,
     pub modulation_params: LoRaModulationParams,
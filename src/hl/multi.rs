@@ -0,0 +1,100 @@
+//! Several radios receiving concurrently.
+//!
+//! The radios may share an SPI bus, with one [SpiDevice] per radio (e.g. from `embedded-hal-bus` or
+//! `embassy-embedded-hal`). The bus is only held during SPI transactions, not while waiting for DIO1.
+
+use core::convert::Infallible;
+
+use embassy_futures::select::select_array;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{SX128X, frontend::RfFrontend, lora::LoRaPacketStatus};
+
+/// A packet received by one of the radios of [Radios].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet {
+    /// Index of the radio that received the packet.
+    pub radio: usize,
+    pub len: usize,
+    pub status: LoRaPacketStatus,
+}
+
+/// `N` radios listening at the same time, e.g. one per channel or spreading factor.
+pub struct Radios<T, BUSY, DIO, NRESET, DELAY, FE, const N: usize>
+where
+    T: SpiDevice,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+{
+    radios: [SX128X<T, BUSY, DIO, NRESET, DELAY, FE>; N],
+    len: usize,
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+    const N: usize,
+> Radios<T, BUSY, DIO, NRESET, DELAY, FE, N>
+{
+    /// Take over configured radios.
+    pub fn new(radios: [SX128X<T, BUSY, DIO, NRESET, DELAY, FE>; N]) -> Self {
+        Self { radios, len: 0 }
+    }
+
+    /// Access a single radio. Call [Radios::listen] after reconfiguring it.
+    pub fn radio(&mut self, index: usize) -> &mut SX128X<T, BUSY, DIO, NRESET, DELAY, FE> {
+        &mut self.radios[index]
+    }
+
+    pub fn into_inner(self) -> [SX128X<T, BUSY, DIO, NRESET, DELAY, FE>; N] {
+        self.radios
+    }
+
+    /// Put every radio in continuous RX, for packets of at most `len` bytes.
+    pub async fn listen(&mut self, len: usize) -> Result<(), E> {
+        self.len = len;
+        for radio in &mut self.radios {
+            radio.listen(len).await?;
+        }
+        Ok(())
+    }
+
+    /// Wait for the next valid packet on any radio.
+    ///
+    /// Packets with a CRC or header error are dropped. When several radios have a packet pending,
+    /// the lowest index is read first.
+    pub async fn receive(&mut self, buf: &mut [u8]) -> Result<Packet, E> {
+        loop {
+            let (_, radio) = select_array(
+                self.radios
+                    .each_mut()
+                    .map(|radio| radio.dio1.wait_for_high()),
+            )
+            .await;
+
+            if let Some((len, status)) = self.radios[radio].take_listened(buf).await? {
+                return Ok(Packet { radio, len, status });
+            }
+        }
+    }
+
+    /// Transmit on one radio, which then returns to listening. The others keep receiving.
+    ///
+    /// A packet the radio is receiving at that moment is lost.
+    pub async fn send(&mut self, index: usize, buf: &[u8]) -> Result<(), E> {
+        let radio = &mut self.radios[index];
+        radio.abort().await?;
+        radio.send(buf).await?;
+        radio.listen(self.len).await
+    }
+}
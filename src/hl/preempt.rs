@@ -0,0 +1,56 @@
+use core::{convert::Infallible, future::Future};
+
+use embassy_futures::select::{Either, select};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{SX128X, frontend::RfFrontend, lora::LoRaPacketStatus};
+
+/// How [SX128X::receive_preemptible] ended.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RxOutcome<P> {
+    Packet(usize, LoRaPacketStatus),
+    /// The RX operation ended without a valid packet, after a CRC or header error.
+    Nothing,
+    /// The preempting future completed first, with this output.
+    Preempted(P),
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    /// Receive a packet unless `preempt` completes first, e.g. a request from another task to transmit.
+    ///
+    /// When preempted the chip is put back to standby with its IRQs cleared, ready for the next
+    /// operation. A packet completing at the same time is dropped.
+    pub async fn receive_preemptible<P>(
+        &mut self,
+        buf: &mut [u8],
+        preempt: impl Future<Output = P>,
+    ) -> Result<RxOutcome<P>, E> {
+        self.prepare_rx(buf.len()).await?;
+        self.start_rx().await?;
+
+        match select(self.dio1.wait_for_high(), preempt).await {
+            Either::First(_) => {
+                self.frontend.set_state(self.idle_state());
+                Ok(match self.take_packet(buf).await? {
+                    Some((len, status)) => RxOutcome::Packet(len, status),
+                    None => RxOutcome::Nothing,
+                })
+            }
+            Either::Second(output) => {
+                self.abort().await?;
+                Ok(RxOutcome::Preempted(output))
+            }
+        }
+    }
+}
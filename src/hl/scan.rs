@@ -0,0 +1,97 @@
+use core::convert::Infallible;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    ChannelPlan, SX128X,
+    frontend::{RfFrontend, RfState},
+    irq::Irq,
+};
+use crate::ll;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ScanParams {
+    /// Number of RSSI samples averaged per channel.
+    pub samples: u8,
+    /// Time between entering RX or taking a sample, and taking the next sample.
+    pub interval_us: u32,
+}
+
+impl Default for ScanParams {
+    fn default() -> Self {
+        Self {
+            samples: 8,
+            interval_us: 100,
+        }
+    }
+}
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    /// Instantaneous RSSI in dBm. Only meaningful in RX mode.
+    ///
+    /// Unlike other operations it leaves an ongoing receive running, e.g. one started with
+    /// [SX128X::listen], as that is when the RSSI is sampled.
+    pub async fn rssi_inst(&mut self) -> Result<i16, E> {
+        let rssi = self.ll.get_rssi_inst().dispatch_async().await?;
+        Ok(-(rssi.rssi_inst() as i16) / 2)
+    }
+
+    /// Measure the noise floor in dBm of every channel in `plan`.
+    ///
+    /// Fills `table` by channel index, up to its length. Returns to standby on the configured
+    /// frequency afterwards.
+    pub async fn scan(
+        &mut self,
+        plan: &ChannelPlan,
+        params: ScanParams,
+        table: &mut [i16],
+    ) -> Result<(), E> {
+        let samples = core::cmp::max(params.samples, 1);
+        self.recover().await?;
+        self.in_flight = true;
+
+        for (frequency, entry) in plan.channels().zip(table.iter_mut()) {
+            self.set_standbyrc().await?;
+            self.set_rf_frequency(frequency).await?;
+
+            self.frontend.set_state(RfState::Rx);
+            self.ll
+                .set_rx()
+                .dispatch_async(|cmd| {
+                    cmd.set_period_base(ll::RxTimeoutStep::Step15Us625);
+                    cmd.set_period_base_count(ll::RxTimeoutBaseCount::Continuous);
+                })
+                .await?;
+
+            let mut total = 0i32;
+            for _ in 0..samples {
+                self.delay.delay_us(params.interval_us).await;
+                total += self.rssi_inst().await? as i32;
+            }
+            *entry = (total / samples as i32) as i16;
+        }
+
+        self.set_standbyrc().await?;
+        self.set_rf_frequency(self.params.frequency).await?;
+
+        // Any packets received during the scan are of no interest, but may have overwritten the loaded payload.
+        self.ll
+            .clr_irq_status()
+            .dispatch_async(|cmd| cmd.set_value(Irq::all().bits()))
+            .await?;
+        self.forget_tx_reachable_by_rx();
+        self.in_flight = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! A radio task exchanging packets with the application through `embassy-sync` channels.
+//!
+//! [run] owns the radio and receives until a packet is queued for transmission, which preempts
+//! reception. Since embassy tasks cannot be generic, wrap it in a task of your own:
+//!
+//! ```text
+//! static CHANNELS: RadioChannels<CriticalSectionRawMutex, 64, 4, 4> = RadioChannels::new();
+//!
+//! #[embassy_executor::task]
+//! async fn radio_task(mut radio: Radio) {
+//!     radio.configure().await.unwrap();
+//!     let _ = task::run(&mut radio, CHANNELS.tx.receiver(), CHANNELS.rx.sender()).await;
+//! }
+//! ```
+
+use core::convert::Infallible;
+
+use embassy_sync::{
+    blocking_mutex::raw::RawMutex,
+    channel::{Channel, Receiver, Sender},
+};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{SX128X, frontend::RfFrontend, lora::LoRaPacketStatus, preempt::RxOutcome};
+
+/// A payload of up to `MTU` bytes queued for transmission.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxPacket<const MTU: usize> {
+    data: [u8; MTU],
+    len: usize,
+}
+
+impl<const MTU: usize> TxPacket<MTU> {
+    /// Copy `payload`, or `None` if it exceeds `MTU` bytes.
+    pub fn new(payload: &[u8]) -> Option<Self> {
+        const { ::core::assert!(MTU <= 255, "LoRa payloads are limited to 255 bytes") };
+        if payload.len() > MTU {
+            return None;
+        }
+        let mut data = [0; MTU];
+        data[..payload.len()].copy_from_slice(payload);
+        Some(Self {
+            data,
+            len: payload.len(),
+        })
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+/// A received packet with its metadata.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RxPacket<const MTU: usize> {
+    data: [u8; MTU],
+    len: usize,
+    pub status: LoRaPacketStatus,
+}
+
+impl<const MTU: usize> RxPacket<MTU> {
+    pub fn payload(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+/// The queues between the application and [run], `TX` and `RX` packets deep.
+///
+/// Not `defmt::Format`, as the `embassy-sync` channels do not implement it.
+pub struct RadioChannels<M: RawMutex, const MTU: usize, const TX: usize, const RX: usize> {
+    pub tx: Channel<M, TxPacket<MTU>, TX>,
+    pub rx: Channel<M, RxPacket<MTU>, RX>,
+}
+
+impl<M: RawMutex, const MTU: usize, const TX: usize, const RX: usize>
+    RadioChannels<M, MTU, TX, RX>
+{
+    pub const fn new() -> Self {
+        Self {
+            tx: Channel::new(),
+            rx: Channel::new(),
+        }
+    }
+}
+
+impl<M: RawMutex, const MTU: usize, const TX: usize, const RX: usize> Default
+    for RadioChannels<M, MTU, TX, RX>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Receive into `rx` and transmit whatever arrives on `tx`, until an SPI error occurs.
+///
+/// The radio must be configured beforehand. Packets received while `rx` is full are dropped,
+/// so that a slow consumer does not stall the radio.
+pub async fn run<
+    T,
+    BUSY,
+    DIO,
+    NRESET,
+    DELAY,
+    FE,
+    E,
+    M,
+    const MTU: usize,
+    const TX: usize,
+    const RX: usize,
+>(
+    radio: &mut SX128X<T, BUSY, DIO, NRESET, DELAY, FE>,
+    tx: Receiver<'_, M, TxPacket<MTU>, TX>,
+    rx: Sender<'_, M, RxPacket<MTU>, RX>,
+) -> Result<Infallible, E>
+where
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    M: RawMutex,
+{
+    const { ::core::assert!(MTU <= 255, "LoRa payloads are limited to 255 bytes") };
+    let mut data = [0; MTU];
+    loop {
+        match radio.receive_preemptible(&mut data, tx.receive()).await? {
+            RxOutcome::Packet(len, status) => {
+                if rx.try_send(RxPacket { data, len, status }).is_err() {
+                    warn!("RX queue full, dropping packet");
+                }
+            }
+            RxOutcome::Nothing => {}
+            RxOutcome::Preempted(packet) => radio.send(packet.payload()).await?,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! RF test modes, e.g. for certification.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    Frequency, SX128X, TxParams,
+    frontend::{RfFrontend, RfState},
+};
+
+impl<
+    T: SpiDevice<Error = E>,
+    BUSY: Wait<Error = Infallible>,
+    DIO: Wait<Error = Infallible>,
+    NRESET: OutputPin<Error = Infallible>,
+    DELAY: DelayNs,
+    FE: RfFrontend,
+    E,
+> SX128X<T, BUSY, DIO, NRESET, DELAY, FE>
+{
+    /// Emit an unmodulated carrier until [SX128X::stop_test_mode] is called.
+    pub async fn start_continuous_wave(
+        &mut self,
+        frequency: Frequency,
+        tx_params: TxParams,
+    ) -> Result<(), E> {
+        self.prepare_test_mode(frequency, tx_params).await?;
+        self.ll.set_tx_continuous_wave().dispatch_async().await
+    }
+
+    /// Emit a continuous preamble with the configured modulation until [SX128X::stop_test_mode] is called.
+    pub async fn start_continuous_preamble(
+        &mut self,
+        frequency: Frequency,
+        tx_params: TxParams,
+    ) -> Result<(), E> {
+        self.prepare_test_mode(frequency, tx_params).await?;
+        self.ll.set_tx_continuous_preamble().dispatch_async().await
+    }
+
+    /// Return to standby, restoring the frequency and TX parameters of the modem configuration.
+    ///
+    /// Starting any other operation instead does the same, with a warning.
+    pub async fn stop_test_mode(&mut self) -> Result<(), E> {
+        self.set_standbyrc().await?;
+        self.set_rf_frequency(self.params.frequency).await?;
+        self.set_tx_params(self.params.tx_params).await?;
+        self.in_flight = false;
+        Ok(())
+    }
+
+    async fn prepare_test_mode(
+        &mut self,
+        frequency: Frequency,
+        tx_params: TxParams,
+    ) -> Result<(), E> {
+        self.recover().await?;
+        self.set_standbyrc().await?;
+        self.set_rf_frequency(frequency).await?;
+        self.set_tx_params(tx_params).await?;
+        self.frontend.set_state(RfState::Tx);
+        self.in_flight = true;
+        Ok(())
+    }
+}
@@ -6,5 +6,11 @@ pub(crate) mod fmt;
 #[cfg(test)]
 mod test;
 
+#[cfg(any(test, feature = "capture"))]
+pub mod capture;
 pub mod hl;
 pub mod ll;
+#[cfg(any(test, feature = "trace", feature = "capture"))]
+mod names;
+#[cfg(any(test, feature = "sim"))]
+pub mod sim;
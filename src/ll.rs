@@ -6,9 +6,27 @@ use device_driver::{
 use embedded_hal::spi::Operation;
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
+#[cfg(any(test, feature = "trace"))]
+use crate::{fmt::Bytes, names};
+
+/// What [Interface] logs of the SPI traffic, at trace level.
+#[cfg(any(test, feature = "trace"))]
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Verbosity {
+    #[default]
+    Off,
+    /// The name and raw bytes of every command, register and buffer access.
+    Accesses,
+    /// Also the values of the fields of commands and registers.
+    Fields,
+}
+
 pub struct Interface<SPI, BUSY> {
     spi: SPI,
     busy: BUSY,
+    #[cfg(any(test, feature = "trace"))]
+    verbosity: Verbosity,
 }
 
 impl<SPI, BUSY> AsyncCommandInterface for Interface<SPI, BUSY>
@@ -63,6 +81,8 @@ where
         }
 
         let _ = self.busy.wait_for_low().await;
+        #[cfg(any(test, feature = "trace"))]
+        self.trace_command(address, input, output);
         Ok(())
     }
 }
@@ -89,6 +109,8 @@ where
         let _ = self.busy.wait_for_low().await;
         self.spi.transaction(&mut operations).await?;
         let _ = self.busy.wait_for_low().await;
+        #[cfg(any(test, feature = "trace"))]
+        self.trace_register("WRITE_REGISTER", u16::from_be_bytes(address), data);
         Ok(())
     }
 
@@ -106,6 +128,8 @@ where
         let _ = self.busy.wait_for_low().await;
         self.spi.transaction(&mut operations).await?;
         let _ = self.busy.wait_for_low().await;
+        #[cfg(any(test, feature = "trace"))]
+        self.trace_register("READ_REGISTER", u16::from_be_bytes(address), data);
         Ok(())
     }
 }
@@ -135,6 +159,10 @@ where
         let _ = self.busy.wait_for_low().await;
         self.spi.transaction(&mut operations).await?;
         let _ = self.busy.wait_for_low().await;
+        #[cfg(any(test, feature = "trace"))]
+        if self.verbosity != Verbosity::Off {
+            trace!("WRITE_BUFFER @{} {}", address, Bytes(buf));
+        }
 
         Ok(buf.len())
     }
@@ -155,6 +183,10 @@ where
         let _ = self.busy.wait_for_low().await;
         self.spi.transaction(&mut operations).await?;
         let _ = self.busy.wait_for_low().await;
+        #[cfg(any(test, feature = "trace"))]
+        if self.verbosity != Verbosity::Off {
+            trace!("READ_BUFFER @{} -> {}", address, Bytes(buf));
+        }
 
         Ok(buf.len())
     }
@@ -162,7 +194,73 @@ where
 
 impl<SPI, BUSY> Interface<SPI, BUSY> {
     pub const fn new(spi: SPI, busy: BUSY) -> Self {
-        Self { spi, busy }
+        Self {
+            spi,
+            busy,
+            #[cfg(any(test, feature = "trace"))]
+            verbosity: Verbosity::Off,
+        }
+    }
+
+    #[cfg(any(test, feature = "trace"))]
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Log the SPI traffic, decoded with the names from `device.yaml`.
+    #[cfg(any(test, feature = "trace"))]
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    #[cfg(any(test, feature = "trace"))]
+    fn trace_command(&self, opcode: u8, input: &[u8], output: &[u8]) {
+        if self.verbosity == Verbosity::Off {
+            return;
+        }
+        match names::command(opcode) {
+            Some((name, true)) => trace!("{} -> {}", name, Bytes(output)),
+            Some((name, false)) => trace!("{} {}", name, Bytes(input)),
+            None => trace!("UNKNOWN {:#x} {}", opcode, Bytes(input)),
+        }
+        if self.verbosity == Verbosity::Fields {
+            names::command_fields(opcode, input, output);
+        }
+    }
+
+    #[cfg(any(test, feature = "trace"))]
+    fn trace_register(&self, access: &str, address: u16, data: &[u8]) {
+        if self.verbosity == Verbosity::Off {
+            return;
+        }
+        match names::register(address) {
+            Some((name, 0)) => trace!("{} {} {}", access, name, Bytes(data)),
+            Some((name, offset)) => trace!("{} {}+{} {}", access, name, offset, Bytes(data)),
+            None => trace!("{} {:#x} {}", access, address, Bytes(data)),
+        }
+        if self.verbosity == Verbosity::Fields {
+            names::register_fields(address, data);
+        }
+    }
+
+    /// Wake the chip from sleep mode.
+    ///
+    /// BUSY stays high while sleeping, so unlike the other operations this does not wait for it beforehand.
+    /// Any falling edge on NSS wakes the chip, for which a `GET_STATUS` is issued.
+    pub async fn wake(&mut self) -> Result<(), SPI::Error>
+    where
+        SPI: SpiDevice,
+        BUSY: Wait<Error = Infallible>,
+    {
+        self.spi
+            .transaction(&mut [Operation::Write(&[0xC0]), Operation::Write(&[0x00])])
+            .await?;
+        let _ = self.busy.wait_for_low().await;
+        #[cfg(any(test, feature = "trace"))]
+        if self.verbosity != Verbosity::Off {
+            trace!("wake");
+        }
+        Ok(())
     }
 
     pub fn take(self) -> (SPI, BUSY) {
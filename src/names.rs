@@ -0,0 +1,24 @@
+//! Names of the commands and registers in `device.yaml`, extracted by the build script.
+
+include!(concat!(env!("OUT_DIR"), "/names.rs"));
+
+/// The name of the command with `opcode`, and whether it reads data back.
+///
+/// Commands with per-packet-type variants resolve to the raw variant, which is listed first.
+pub fn command(opcode: u8) -> Option<(&'static str, bool)> {
+    COMMANDS
+        .iter()
+        .find(|(address, _, _)| *address == opcode)
+        .map(|(_, name, read)| (*name, *read))
+}
+
+/// The name of the register holding `address`, and the offset of `address` within it.
+pub fn register(address: u16) -> Option<(&'static str, u16)> {
+    REGISTERS
+        .iter()
+        .filter(|(start, size_bits, _)| {
+            (*start..*start + size_bits.div_ceil(8) as u16).contains(&address)
+        })
+        .map(|(start, _, name)| (*name, address - start))
+        .min_by_key(|(_, offset)| *offset)
+}
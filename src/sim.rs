@@ -0,0 +1,877 @@
+//! Behavioural SX128x simulator for host-side testing.
+//!
+//! [SimChip] models the chip at the level of its SPI protocol: it decodes each transaction into a command,
+//! tracks the registers, data buffer, operating mode and IRQs, and answers reads like the real chip would.
+//! Unlike transaction scripts this does not care about the exact order of harmless operations,
+//! so [hl::SX128X] can be tested end-to-end against it.
+//!
+//! Transmissions complete instantly and are recorded, see [SimChip::take_transmitted].
+//! Packets to be received are injected with [SimChip::deliver].
+//!
+//! Several chips can share a virtual RF medium, see [air::Air].
+//!
+//! Not modelled: timing, modem performance and the register side-effects of most settings.
+
+extern crate std;
+
+pub mod air;
+
+use core::{
+    convert::Infallible,
+    future::poll_fn,
+    task::{Poll, Waker},
+};
+use std::{
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    vec::Vec,
+};
+
+use embedded_hal::{
+    digital::{ErrorType as PinErrorType, OutputPin},
+    spi::{ErrorType as SpiErrorType, Operation},
+};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use crate::{
+    hl::{self, Frequency, irq::Irq, lora::LoRaModemParams},
+    ll::PacketType,
+};
+
+/// [hl::SX128X] wired to a [SimChip].
+pub type SimRadio = hl::SX128X<SimSpi, SimBusy, SimDio, SimReset, SimDelay>;
+
+const REGISTER_SPACE: usize = 0x1000;
+const BUFFER_SIZE: usize = 256;
+
+/// Register reset values, as documented in the register map.
+const REGISTER_RESET_VALUES: &[(u16, &[u8])] = &[
+    (0x153, &[0xA9, 0xB7]),
+    (0x891, &[0x25]),
+    (0x895, &[0x01]),
+    (0x89E, &[0x0A]),
+    (0x89F, &[0x4D]),
+    (0x8C2, &[0x04]),
+    (0x912, &[0x00, 0x00, 0x00, 0x19]),
+    (0x916, &[0x00, 0x00, 0x00, 0x19]),
+    (0x924, &[0x03]),
+    (0x92B, &[0x00, 0x5F, 0xD2]),
+    (0x931, &[0x03]),
+    (0x942, &[0x32]),
+    (0x944, &[0x14, 0x24]),
+    (0x9C5, &[0x01]),
+    (0x9C6, &[0xFF, 0xFF]),
+    (0x9C8, &[0xFF, 0xFF]),
+    (0x9CD, &[0x80]),
+    (0x9CE, &[0x55, 0x55, 0x55, 0x55, 0x55]),
+    (0x9D3, &[0x55, 0x55, 0x55, 0x55, 0x55]),
+    (0x9D8, &[0x55, 0x55, 0x55, 0x55, 0x55]),
+];
+
+/// Operating mode of the simulated chip.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Sleep,
+    StandbyRc,
+    StandbyXosc,
+    Fs,
+    Tx,
+    Rx,
+}
+
+impl Mode {
+    fn circuit_mode(self) -> u8 {
+        match self {
+            // The status cannot be read while sleeping, report the mode the chip wakes into.
+            Mode::Sleep | Mode::StandbyRc => 0x2,
+            Mode::StandbyXosc => 0x3,
+            Mode::Fs => 0x4,
+            Mode::Rx => 0x5,
+            Mode::Tx => 0x6,
+        }
+    }
+}
+
+/// The RX operation the chip is performing.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum RxKind {
+    /// Until a packet is received.
+    Single,
+    /// Until a packet is received or the timeout expires.
+    Timed,
+    /// Receiving packets until told otherwise.
+    Continuous,
+}
+
+/// A packet sent by the simulated chip.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Transmission {
+    pub frequency: Frequency,
+    pub packet_type: Option<PacketType>,
+    /// Raw `SET_MODULATION_PARAMS` arguments.
+    pub modulation_params: [u8; 3],
+    /// Raw `SET_PACKET_PARAMS` arguments.
+    pub packet_params: [u8; 7],
+    pub power_dbm: i8,
+    pub payload: Vec<u8>,
+}
+
+/// A packet to be received by the simulated chip.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Incoming {
+    pub payload: Vec<u8>,
+    pub rssi_dbm: i16,
+    pub snr_db: i8,
+    /// Whether the packet arrives with a CRC error.
+    pub crc_error: bool,
+}
+
+impl Incoming {
+    /// A clean packet at -60 dBm with 10 dB SNR.
+    pub fn new(payload: &[u8]) -> Self {
+        Self {
+            payload: payload.to_vec(),
+            rssi_dbm: -60,
+            snr_db: 10,
+            crc_error: false,
+        }
+    }
+}
+
+/// Settings kept in data RAM, lost when sleeping without retention.
+#[derive(Clone, Debug)]
+struct Config {
+    packet_type: Option<PacketType>,
+    frequency: [u8; 3],
+    modulation_params: [u8; 3],
+    packet_params: [u8; 7],
+    tx_params: [u8; 2],
+    cad_params: u8,
+    tx_base: u8,
+    rx_base: u8,
+    irq_mask: u16,
+    dio1_mask: u16,
+    auto_fs: bool,
+    auto_tx: u16,
+    long_preamble: bool,
+    ranging_initiator: bool,
+    registers: Vec<u8>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut registers = std::vec![0; REGISTER_SPACE];
+        for (address, value) in REGISTER_RESET_VALUES {
+            let address = *address as usize;
+            registers[address..address + value.len()].copy_from_slice(value);
+        }
+
+        Self {
+            packet_type: Some(PacketType::Gfsk),
+            frequency: [0; 3],
+            modulation_params: [0; 3],
+            packet_params: [0; 7],
+            tx_params: [0; 2],
+            cad_params: 0,
+            tx_base: 0,
+            rx_base: 0,
+            irq_mask: 0,
+            dio1_mask: 0,
+            auto_fs: false,
+            auto_tx: 0,
+            long_preamble: false,
+            ranging_initiator: false,
+            registers,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    mode: Mode,
+    rx: RxKind,
+    /// Retention flags of the current sleep, `(ram, buffer)`.
+    sleep: (bool, bool),
+    command_status: u8,
+    config: Config,
+    saved: Option<Config>,
+    buffer: [u8; BUFFER_SIZE],
+    irq_status: u16,
+    rx_payload_length: u8,
+    rx_start_pointer: u8,
+    packet_status: [u8; 5],
+    rssi_inst_dbm: i16,
+    incoming: Vec<Incoming>,
+    transmitted: Vec<Transmission>,
+    /// The medium the chip is attached to, and its node index there.
+    air: Option<(Weak<Mutex<air::AirState>>, usize)>,
+    /// Transmissions not yet handed to the medium.
+    outbox: Vec<Transmission>,
+    wakers: Vec<Waker>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            mode: Mode::StandbyRc,
+            rx: RxKind::Single,
+            sleep: (false, false),
+            command_status: 0x1,
+            config: Config::default(),
+            saved: None,
+            buffer: [0; BUFFER_SIZE],
+            irq_status: 0,
+            rx_payload_length: 0,
+            rx_start_pointer: 0,
+            packet_status: [0; 5],
+            rssi_inst_dbm: -100,
+            incoming: Vec::new(),
+            transmitted: Vec::new(),
+            air: None,
+            outbox: Vec::new(),
+            wakers: Vec::new(),
+        }
+    }
+}
+
+impl State {
+    fn status(&self) -> u8 {
+        (self.mode.circuit_mode() << 5) | (self.command_status << 2)
+    }
+
+    /// The byte shifted out on MISO at `position` of a transaction starting with `mosi`.
+    fn miso(&self, mosi: &[u8], position: usize) -> u8 {
+        let opcode = mosi.first().copied().unwrap_or(0xC0);
+        let data = |offset: usize| position.checked_sub(offset);
+
+        match opcode {
+            // READ_REGISTER: opcode, address, NOP, data...
+            0x19 if mosi.len() >= 3 => match data(4) {
+                Some(index) => {
+                    let address = u16::from_be_bytes([mosi[1], mosi[2]]) as usize + index;
+                    self.config.registers[address % REGISTER_SPACE]
+                }
+                None => self.status(),
+            },
+            // READ_BUFFER: opcode, offset, NOP, data...
+            0x1B if mosi.len() >= 2 => match data(3) {
+                Some(index) => self.buffer[(mosi[1] as usize + index) % BUFFER_SIZE],
+                None => self.status(),
+            },
+            // Other GET commands: opcode, NOP, data...
+            0x03 | 0x15 | 0x17 | 0x1D | 0x1F => match data(2) {
+                Some(index) => self.response(opcode).get(index).copied().unwrap_or(0),
+                None => self.status(),
+            },
+            _ => self.status(),
+        }
+    }
+
+    fn response(&self, opcode: u8) -> Vec<u8> {
+        match opcode {
+            0x03 => std::vec![self.config.packet_type.map_or(0xFF, u8::from)],
+            0x15 => self.irq_status.to_be_bytes().to_vec(),
+            0x17 => std::vec![self.rx_payload_length, self.rx_start_pointer],
+            0x1D => self.packet_status.to_vec(),
+            0x1F => std::vec![rssi_to_raw(self.rssi_inst_dbm)],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Execute the command in `mosi`, after the transaction finished.
+    fn execute(&mut self, mosi: &[u8]) {
+        let Some((&opcode, args)) = mosi.split_first() else {
+            return;
+        };
+
+        // Any transaction wakes the chip, but is not executed.
+        if self.mode == Mode::Sleep {
+            self.wake();
+            return;
+        }
+
+        let arg = |index: usize| args.get(index).copied().unwrap_or(0);
+        let arg16 = |index: usize| u16::from_be_bytes([arg(index), arg(index + 1)]);
+
+        // Reads have no side-effects, and leave the status of the previous command.
+        if matches!(
+            opcode,
+            0xC0 | 0x03 | 0x15 | 0x17 | 0x1D | 0x1F | 0x19 | 0x1B
+        ) {
+            return;
+        }
+
+        self.command_status = 0x1;
+        match opcode {
+            0x18 if args.len() >= 2 => {
+                let address = arg16(0) as usize;
+                for (index, value) in args[2..].iter().enumerate() {
+                    self.config.registers[(address + index) % REGISTER_SPACE] = *value;
+                }
+            }
+            0x1A if !args.is_empty() => {
+                let offset = args[0] as usize;
+                for (index, value) in args[1..].iter().enumerate() {
+                    self.buffer[(offset + index) % BUFFER_SIZE] = *value;
+                }
+            }
+            0x84 => {
+                self.sleep = (arg(0) & 0x01 != 0, arg(0) & 0x02 != 0);
+                self.mode = Mode::Sleep;
+            }
+            0x80 => {
+                self.mode = if arg(0) & 0x01 == 0 {
+                    Mode::StandbyRc
+                } else {
+                    Mode::StandbyXosc
+                };
+            }
+            0xC1 => self.mode = Mode::Fs,
+            0x83 => {
+                self.mode = Mode::Tx;
+                self.transmit();
+            }
+            0x82 => {
+                self.rx = match arg16(1) {
+                    0x0000 => RxKind::Single,
+                    0xFFFF => RxKind::Continuous,
+                    _ => RxKind::Timed,
+                };
+                self.mode = Mode::Rx;
+                self.poll_incoming();
+            }
+            0x94 => {
+                self.rx = RxKind::Single;
+                self.mode = Mode::Rx;
+                self.poll_incoming();
+            }
+            0xC5 => {
+                let mut irqs = Irq::CadDone;
+                if !self.incoming.is_empty() {
+                    irqs |= Irq::CadActivityDetected;
+                }
+                self.raise(irqs);
+                self.mode = self.idle_mode();
+            }
+            0xD1 | 0xD2 => self.mode = Mode::Tx,
+            0x8A => self.config.packet_type = PacketType::try_from(arg(0)).ok(),
+            0x86 => self.config.frequency = [arg(0), arg(1), arg(2)],
+            0x8E => self.config.tx_params = [arg(0), arg(1)],
+            0x88 => self.config.cad_params = arg(0),
+            0x8F => {
+                self.config.tx_base = arg(0);
+                self.config.rx_base = arg(1);
+            }
+            0x8B => {
+                for (index, value) in self.config.modulation_params.iter_mut().enumerate() {
+                    *value = arg(index);
+                }
+            }
+            0x8C => {
+                for (index, value) in self.config.packet_params.iter_mut().enumerate() {
+                    *value = arg(index);
+                }
+            }
+            0x8D => {
+                self.config.irq_mask = arg16(0);
+                self.config.dio1_mask = arg16(2);
+                self.wake_waiters();
+            }
+            0x97 => {
+                self.irq_status &= !arg16(0);
+                self.wake_waiters();
+            }
+            0xA3 => self.config.ranging_initiator = arg(0) == 0x01,
+            0x96 | 0x89 | 0x9A => {}
+            0xD5 => self.saved = Some(self.config.clone()),
+            0x9E => self.config.auto_fs = arg(0) & 0x01 != 0,
+            0x98 => self.config.auto_tx = arg16(0),
+            0x9B => self.config.long_preamble = arg(0) & 0x01 != 0,
+            _ => self.command_status = 0x4,
+        }
+    }
+
+    fn wake(&mut self) {
+        let (ram_retention, buffer_retention) = self.sleep;
+        if ram_retention {
+            if let Some(saved) = &self.saved {
+                self.config = saved.clone();
+            }
+        } else {
+            self.config = Config::default();
+            self.saved = None;
+        }
+        if !buffer_retention {
+            self.buffer = [0; BUFFER_SIZE];
+        }
+        self.irq_status = 0;
+        self.mode = Mode::StandbyRc;
+        self.wake_waiters();
+    }
+
+    fn reset(&mut self) {
+        let incoming = core::mem::take(&mut self.incoming);
+        let transmitted = core::mem::take(&mut self.transmitted);
+        let air = self.air.take();
+        let wakers = core::mem::take(&mut self.wakers);
+        *self = State {
+            incoming,
+            transmitted,
+            air,
+            wakers,
+            ..State::default()
+        };
+        self.wake_waiters();
+    }
+
+    /// The mode the chip falls back to after TX or RX.
+    fn idle_mode(&self) -> Mode {
+        if self.config.auto_fs {
+            Mode::Fs
+        } else {
+            Mode::StandbyRc
+        }
+    }
+
+    /// The payload length set with `SET_PACKET_PARAMS`.
+    fn payload_length(&self) -> usize {
+        let params = &self.config.packet_params;
+        match self.config.packet_type {
+            Some(PacketType::LoRa) | Some(PacketType::Ranging) => params[2] as usize,
+            Some(PacketType::Ble) => {
+                self.buffer[(self.config.tx_base as usize + 1) % BUFFER_SIZE] as usize + 2
+            }
+            _ => params[4] as usize,
+        }
+    }
+
+    fn transmit(&mut self) {
+        let base = self.config.tx_base as usize;
+        let payload = (0..self.payload_length())
+            .map(|index| self.buffer[(base + index) % BUFFER_SIZE])
+            .collect();
+
+        let transmission = Transmission {
+            frequency: Frequency::from_bytes(self.config.frequency),
+            packet_type: self.config.packet_type,
+            modulation_params: self.config.modulation_params,
+            packet_params: self.config.packet_params,
+            power_dbm: (self.config.tx_params[0] & 0x1F) as i8 - 18,
+            payload,
+        };
+        if self.air.is_some() {
+            self.outbox.push(transmission.clone());
+        }
+        self.transmitted.push(transmission);
+        self.command_status = 0x6;
+        self.raise(Irq::TxDone);
+        self.mode = self.idle_mode();
+    }
+
+    /// Receive the next incoming packet, if listening.
+    fn poll_incoming(&mut self) {
+        while self.mode == Mode::Rx && !self.incoming.is_empty() {
+            let packet = self.incoming.remove(0);
+            self.receive(packet);
+        }
+    }
+
+    fn listens_to(&self, transmission: &Transmission) -> bool {
+        self.mode == Mode::Rx
+            && self.config.frequency == transmission.frequency.as_bytes()
+            && self.config.packet_type == transmission.packet_type
+            && self.config.modulation_params == transmission.modulation_params
+    }
+
+    fn receive(&mut self, packet: Incoming) {
+        let base = self.config.rx_base as usize;
+        for (index, value) in packet.payload.iter().enumerate() {
+            self.buffer[(base + index) % BUFFER_SIZE] = *value;
+        }
+        self.rx_payload_length = packet.payload.len() as u8;
+        self.rx_start_pointer = self.config.rx_base;
+
+        let rssi = rssi_to_raw(packet.rssi_dbm);
+        let mut irqs = Irq::PreambleDetected | Irq::RxDone;
+        match self.config.packet_type {
+            Some(PacketType::LoRa) | Some(PacketType::Ranging) => {
+                irqs |= Irq::HeaderValid;
+                self.packet_status = [rssi, (packet.snr_db as u8).wrapping_mul(4), 0, 0, 0];
+                self.config.registers[0x901] = self.rx_payload_length;
+            }
+            _ => {
+                irqs |= Irq::SyncwordValid;
+                let mut errors = 1 << 1; // Packet received.
+                if packet.crc_error {
+                    errors |= 1 << 4;
+                }
+                self.packet_status = [0, rssi, errors, 0, 1];
+            }
+        }
+        if packet.crc_error {
+            irqs |= Irq::CrcError;
+        }
+
+        self.command_status = 0x2;
+        self.raise(irqs);
+
+        if self.rx != RxKind::Continuous {
+            self.mode = self.idle_mode();
+            if self.config.auto_tx != 0 {
+                self.transmit();
+            }
+        }
+    }
+
+    /// Latch the IRQs enabled in the IRQ mask.
+    fn raise(&mut self, irqs: Irq) {
+        self.irq_status |= irqs.bits() & self.config.irq_mask;
+        self.wake_waiters();
+    }
+
+    fn dio1(&self) -> bool {
+        self.irq_status & self.config.dio1_mask != 0
+    }
+
+    fn wake_waiters(&mut self) {
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Convert dBm to the `-(raw)/2` encoding used by the RSSI fields.
+fn rssi_to_raw(dbm: i16) -> u8 {
+    (-dbm * 2).clamp(0, u8::MAX as i16) as u8
+}
+
+/// A simulated SX128x.
+///
+/// Cloning yields another handle to the same chip.
+#[derive(Clone, Default)]
+pub struct SimChip {
+    state: Arc<Mutex<State>>,
+}
+
+impl SimChip {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// An [hl::SX128X] driving this chip.
+    pub fn radio(&self, params: LoRaModemParams) -> SimRadio {
+        hl::SX128X::new(
+            self.spi(),
+            self.busy(),
+            self.dio1(),
+            self.nreset(),
+            SimDelay,
+            params,
+        )
+    }
+
+    pub fn spi(&self) -> SimSpi {
+        SimSpi { chip: self.clone() }
+    }
+
+    pub fn busy(&self) -> SimBusy {
+        SimBusy
+    }
+
+    pub fn dio1(&self) -> SimDio {
+        SimDio { chip: self.clone() }
+    }
+
+    pub fn nreset(&self) -> SimReset {
+        SimReset { chip: self.clone() }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.state().mode
+    }
+
+    pub fn irq_status(&self) -> Irq {
+        Irq::from_bits_retain(self.state().irq_status)
+    }
+
+    pub fn frequency(&self) -> Frequency {
+        Frequency::from_bytes(self.state().config.frequency)
+    }
+
+    pub fn packet_type(&self) -> Option<PacketType> {
+        self.state().config.packet_type
+    }
+
+    pub fn register(&self, address: u16) -> u8 {
+        self.state().config.registers[address as usize % REGISTER_SPACE]
+    }
+
+    pub fn buffer(&self) -> [u8; BUFFER_SIZE] {
+        self.state().buffer
+    }
+
+    /// Set the RSSI reported by `GET_RSSI_INST`.
+    pub fn set_rssi_inst(&self, dbm: i16) {
+        self.state().rssi_inst_dbm = dbm;
+    }
+
+    /// Queue a packet, received as soon as the chip listens.
+    pub fn deliver(&self, packet: Incoming) {
+        let mut state = self.state();
+        state.incoming.push(packet);
+        state.poll_incoming();
+    }
+
+    /// Expire a timed RX, raising `RxTxTimeout`.
+    pub fn expire_timeout(&self) {
+        let mut state = self.state();
+        if state.mode == Mode::Rx && state.rx == RxKind::Timed {
+            state.mode = state.idle_mode();
+            state.raise(Irq::RxTxTimeout);
+        }
+    }
+
+    /// Receive `packet` if listening with the given settings, returns whether it was received.
+    fn offer(&self, transmission: &Transmission, packet: Incoming) -> bool {
+        let mut state = self.state();
+        if !state.listens_to(transmission) {
+            return false;
+        }
+        state.receive(packet);
+        true
+    }
+
+    /// Whether the chip would hear `transmission` right now.
+    fn listens_to(&self, transmission: &Transmission) -> bool {
+        self.state().listens_to(transmission)
+    }
+
+    /// Whether the chip acts as ranging initiator.
+    fn ranging_initiator(&self) -> bool {
+        self.state().config.ranging_initiator
+    }
+
+    /// Whether a ranging request to `address` is addressed to this chip, as responder.
+    fn ranging_matches(&self, address: u32) -> bool {
+        let state = self.state();
+        let registers = &state.config.registers;
+        let device_address = u32::from_be_bytes([
+            registers[0x916],
+            registers[0x917],
+            registers[0x918],
+            registers[0x919],
+        ]);
+        let checked_bits = 8 * ((registers[0x931] >> 6) as u32 + 1);
+        let mask = u32::MAX >> (32 - checked_bits);
+        !state.config.ranging_initiator && (device_address & mask) == (address & mask)
+    }
+
+    /// The ranging request address, as initiator.
+    fn ranging_request_address(&self) -> u32 {
+        let state = self.state();
+        let registers = &state.config.registers;
+        u32::from_be_bytes([
+            registers[0x912],
+            registers[0x913],
+            registers[0x914],
+            registers[0x915],
+        ])
+    }
+
+    /// Answer a ranging request, as responder.
+    fn respond_ranging(&self) {
+        let mut state = self.state();
+        state.mode = state.idle_mode();
+        state.raise(Irq::RangingResponderRequestValid | Irq::RangingResponderResponseDone);
+    }
+
+    /// Complete a ranging exchange with a raw result or a timeout, as initiator.
+    fn complete_ranging(&self, result: Option<u32>) {
+        let mut state = self.state();
+        match result {
+            Some(result) => {
+                let bytes = result.to_be_bytes();
+                state.config.registers[0x961..0x964].copy_from_slice(&bytes[1..]);
+                state.raise(Irq::RangingInitiatorResultValid);
+            }
+            None => state.raise(Irq::RangingInitiatorResultTimeout),
+        }
+    }
+
+    /// Drain the packets sent so far.
+    pub fn take_transmitted(&self) -> Vec<Transmission> {
+        core::mem::take(&mut self.state().transmitted)
+    }
+}
+
+/// SPI bus of a [SimChip].
+pub struct SimSpi {
+    chip: SimChip,
+}
+
+impl SpiErrorType for SimSpi {
+    type Error = Infallible;
+}
+
+impl SpiDevice for SimSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Infallible> {
+        let mut state = self.chip.state();
+        let mut mosi = Vec::new();
+
+        for operation in operations {
+            match operation {
+                Operation::Read(words) => {
+                    for word in words.iter_mut() {
+                        *word = state.miso(&mosi, mosi.len());
+                        mosi.push(0x00);
+                    }
+                }
+                Operation::Write(words) => mosi.extend_from_slice(words),
+                Operation::Transfer(read, write) => {
+                    for index in 0..read.len().max(write.len()) {
+                        let miso = state.miso(&mosi, mosi.len());
+                        mosi.push(write.get(index).copied().unwrap_or(0x00));
+                        if let Some(word) = read.get_mut(index) {
+                            *word = miso;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(words) => {
+                    for word in words.iter_mut() {
+                        let miso = state.miso(&mosi, mosi.len());
+                        mosi.push(*word);
+                        *word = miso;
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+
+        state.execute(&mosi);
+
+        // Hand transmissions to the medium without holding the lock on the chip.
+        let outbox = core::mem::take(&mut state.outbox);
+        let air = state.air.clone();
+        drop(state);
+        if let Some((air, node)) = air {
+            air::launch(&air, node, outbox);
+        }
+        Ok(())
+    }
+}
+
+/// BUSY pin of a [SimChip], commands complete instantly so it is always low.
+pub struct SimBusy;
+
+impl PinErrorType for SimBusy {
+    type Error = Infallible;
+}
+
+impl Wait for SimBusy {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        core::future::pending().await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        core::future::pending().await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        core::future::pending().await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        core::future::pending().await
+    }
+}
+
+/// DIO1 pin of a [SimChip], high while an IRQ mapped onto it is set.
+pub struct SimDio {
+    chip: SimChip,
+}
+
+impl SimDio {
+    async fn wait_for(&mut self, level: bool) {
+        poll_fn(|cx| {
+            let mut state = self.chip.state();
+            if state.dio1() == level {
+                Poll::Ready(())
+            } else {
+                state.wakers.push(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl PinErrorType for SimDio {
+    type Error = Infallible;
+}
+
+impl Wait for SimDio {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        self.wait_for(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        self.wait_for(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        self.wait_for(false).await;
+        self.wait_for(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        self.wait_for(true).await;
+        self.wait_for(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        let level = self.chip.state().dio1();
+        self.wait_for(!level).await;
+        Ok(())
+    }
+}
+
+/// NRESET pin of a [SimChip], resets the chip when driven low.
+pub struct SimReset {
+    chip: SimChip,
+}
+
+impl PinErrorType for SimReset {
+    type Error = Infallible;
+}
+
+impl OutputPin for SimReset {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.chip.state().reset();
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Delay for a [SimChip], time does not pass in the simulation.
+pub struct SimDelay;
+
+impl DelayNs for SimDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
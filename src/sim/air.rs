@@ -0,0 +1,465 @@
+//! Virtual RF medium connecting several [SimChip]s.
+//!
+//! Packets sent by an attached chip are in flight for their time-on-air, on a virtual clock advanced with
+//! [Air::advance] or [Air::flush]. When a packet ends it reaches every attached chip after the propagation
+//! delay, and is received by those in RX with the same frequency, packet type and modulation, provided the
+//! link budget allows it, it was not lost and it did not collide with an overlapping packet of similar
+//! strength.
+//!
+//! Path loss defaults to free space between the node positions and can be overridden per link.
+//! A ranging request from an initiator is answered by the first matching responder,
+//! with a raw result derived from the distance between both nodes.
+
+extern crate std;
+
+use core::time::Duration;
+use std::{
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    vec::Vec,
+};
+
+use super::{Incoming, SimChip, Transmission};
+use crate::ll::PacketType;
+
+/// Speed of light, in meters per nanosecond.
+const LIGHT_SPEED_M_NS: f64 = 0.299_792_458;
+
+/// Index of a chip attached to an [Air].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct NodeId(usize);
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AirParams {
+    /// Noise power seen by the receivers.
+    pub noise_floor_dbm: f32,
+    /// Probability of losing a packet regardless of the link budget, between 0 and 1.
+    pub packet_loss: f32,
+    /// Power advantage a packet needs over an overlapping one to survive the collision.
+    pub capture_threshold_db: f32,
+    /// Seed of the pseudo-random generator deciding packet loss.
+    pub seed: u64,
+}
+
+impl Default for AirParams {
+    fn default() -> Self {
+        Self {
+            noise_floor_dbm: -105.0,
+            packet_loss: 0.0,
+            capture_threshold_db: 6.0,
+            seed: 1,
+        }
+    }
+}
+
+struct Node {
+    chip: SimChip,
+    position: [f64; 3],
+}
+
+struct Flight {
+    sender: usize,
+    transmission: Transmission,
+    /// Address of the ranging request, if sent by a ranging initiator.
+    ranging: Option<u32>,
+    start_ns: u64,
+    end_ns: u64,
+}
+
+pub(super) struct AirState {
+    params: AirParams,
+    nodes: Vec<Node>,
+    path_loss: Vec<((usize, usize), f32)>,
+    now_ns: u64,
+    rng: u64,
+    in_flight: Vec<Flight>,
+    /// Ended packets that may still overlap with packets in flight.
+    history: Vec<Flight>,
+    /// Outcomes of ended packets and the time they reach their node.
+    arrivals: Vec<(u64, Outcome)>,
+}
+
+/// What happens when a packet ends.
+enum Outcome {
+    Receive(SimChip, Transmission, Incoming),
+    Respond(SimChip),
+    CompleteRanging(SimChip, Option<u32>),
+}
+
+impl AirState {
+    fn distance_m(&self, a: usize, b: usize) -> f64 {
+        let (a, b) = (self.nodes[a].position, self.nodes[b].position);
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn delay_ns(&self, a: usize, b: usize) -> u64 {
+        (self.distance_m(a, b) / LIGHT_SPEED_M_NS) as u64
+    }
+
+    fn path_loss_db(&self, a: usize, b: usize, frequency_hz: u64) -> f32 {
+        let link = (a.min(b), a.max(b));
+        match self.path_loss.iter().find(|(l, _)| *l == link) {
+            Some((_, loss)) => *loss,
+            None => {
+                // Free space path loss, in the far field.
+                let distance = self.distance_m(a, b).max(1.0);
+                (20.0 * distance.log10() + 20.0 * (frequency_hz as f64).log10() - 147.55) as f32
+            }
+        }
+    }
+
+    fn rssi_dbm(&self, flight: &Flight, receiver: usize) -> f32 {
+        let transmission = &flight.transmission;
+        transmission.power_dbm as f32
+            - self.path_loss_db(flight.sender, receiver, transmission.frequency.to_hz())
+    }
+
+    /// Whether another packet overlapping `flight` at `receiver` is too strong for it to be received.
+    fn collides(&self, flight: &Flight, receiver: usize, rssi: f32) -> bool {
+        let arrival = |f: &Flight| {
+            let delay = self.delay_ns(f.sender, receiver);
+            (f.start_ns + delay, f.end_ns + delay)
+        };
+        let (start, end) = arrival(flight);
+
+        self.in_flight
+            .iter()
+            .chain(self.history.iter())
+            .filter(|other| {
+                other.sender != flight.sender
+                    && other.sender != receiver
+                    && other.transmission.frequency == flight.transmission.frequency
+            })
+            .any(|other| {
+                let (other_start, other_end) = arrival(other);
+                other_start < end
+                    && start < other_end
+                    && rssi - self.rssi_dbm(other, receiver) < self.params.capture_threshold_db
+            })
+    }
+
+    /// xorshift64, uniformly distributed in [0, 1).
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Work out who hears the packet that just ended, and when.
+    fn outcomes(&mut self, flight: &Flight) -> Vec<(u64, Outcome)> {
+        let mut outcomes = Vec::new();
+        let min_snr = min_snr_db(&flight.transmission);
+
+        for receiver in 0..self.nodes.len() {
+            let chip = self.nodes[receiver].chip.clone();
+            if receiver == flight.sender || !chip.listens_to(&flight.transmission) {
+                continue;
+            }
+            if let Some(address) = flight.ranging {
+                if !chip.ranging_matches(address) {
+                    continue;
+                }
+            }
+
+            let arrival = flight.end_ns + self.delay_ns(flight.sender, receiver);
+            let rssi = self.rssi_dbm(flight, receiver);
+            let snr = rssi - self.params.noise_floor_dbm;
+            if snr < min_snr || self.collides(flight, receiver, rssi) {
+                continue;
+            }
+            if self.random() < self.params.packet_loss {
+                continue;
+            }
+
+            if flight.ranging.is_some() {
+                let distance = self.distance_m(flight.sender, receiver);
+                let bandwidth = lora_bandwidth_hz(flight.transmission.modulation_params[1]);
+                let result = (distance * 4096.0 * bandwidth / 1e6 / 150.0).round() as u32;
+
+                // The response takes the same path back.
+                let round_trip = 2 * arrival - flight.end_ns;
+                outcomes.push((arrival, Outcome::Respond(chip)));
+                outcomes.push((
+                    round_trip,
+                    Outcome::CompleteRanging(
+                        self.nodes[flight.sender].chip.clone(),
+                        Some(result & 0x00FF_FFFF),
+                    ),
+                ));
+                return outcomes;
+            }
+
+            outcomes.push((
+                arrival,
+                Outcome::Receive(
+                    chip,
+                    flight.transmission.clone(),
+                    Incoming {
+                        payload: flight.transmission.payload.clone(),
+                        rssi_dbm: rssi.round() as i16,
+                        snr_db: snr.clamp(i8::MIN as f32, i8::MAX as f32) as i8,
+                        crc_error: false,
+                    },
+                ),
+            ));
+        }
+
+        if flight.ranging.is_some() {
+            outcomes.push((
+                flight.end_ns,
+                Outcome::CompleteRanging(self.nodes[flight.sender].chip.clone(), None),
+            ));
+        }
+        outcomes
+    }
+}
+
+/// An in-process RF medium.
+///
+/// Cloning yields another handle to the same medium.
+#[derive(Clone)]
+pub struct Air {
+    state: Arc<Mutex<AirState>>,
+}
+
+impl Default for Air {
+    fn default() -> Self {
+        Self::new(AirParams::default())
+    }
+}
+
+impl Air {
+    pub fn new(params: AirParams) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(AirState {
+                params,
+                nodes: Vec::new(),
+                path_loss: Vec::new(),
+                now_ns: 0,
+                rng: params.seed.max(1),
+                in_flight: Vec::new(),
+                history: Vec::new(),
+                arrivals: Vec::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, AirState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Attach `chip` at `position`, in meters.
+    pub fn attach(&self, chip: &SimChip, position: [f64; 3]) -> NodeId {
+        let mut state = self.state();
+        let index = state.nodes.len();
+        state.nodes.push(Node {
+            chip: chip.clone(),
+            position,
+        });
+        chip.state().air = Some((Arc::downgrade(&self.state), index));
+        NodeId(index)
+    }
+
+    pub fn set_position(&self, node: NodeId, position: [f64; 3]) {
+        self.state().nodes[node.0].position = position;
+    }
+
+    /// Override the free space path loss between `a` and `b`, in both directions.
+    pub fn set_path_loss(&self, a: NodeId, b: NodeId, loss_db: Option<f32>) {
+        let link = (a.0.min(b.0), a.0.max(b.0));
+        let mut state = self.state();
+        state.path_loss.retain(|(l, _)| *l != link);
+        if let Some(loss_db) = loss_db {
+            state.path_loss.push((link, loss_db));
+        }
+    }
+
+    /// The virtual time since the medium was created.
+    pub fn now(&self) -> Duration {
+        Duration::from_nanos(self.state().now_ns)
+    }
+
+    /// The number of packets currently on air.
+    pub fn in_flight(&self) -> usize {
+        self.state().in_flight.len()
+    }
+
+    /// Advance the virtual clock, delivering the packets that reach their node in the meantime.
+    pub fn advance(&self, by: Duration) {
+        let deadline = self.state().now_ns + by.as_nanos() as u64;
+
+        loop {
+            let mut state = self.state();
+            let next_end = state
+                .in_flight
+                .iter()
+                .enumerate()
+                .filter(|(_, flight)| flight.end_ns <= deadline)
+                .min_by_key(|(_, flight)| flight.end_ns)
+                .map(|(index, flight)| (index, flight.end_ns));
+            let next_arrival = state
+                .arrivals
+                .iter()
+                .enumerate()
+                .filter(|(_, (time, _))| *time <= deadline)
+                .min_by_key(|(_, (time, _))| *time)
+                .map(|(index, (time, _))| (index, *time));
+
+            // At equal times end the packet first, its outcome may arrive right away.
+            let next_end =
+                next_end.filter(|&(_, end)| next_arrival.is_none_or(|(_, time)| end <= time));
+
+            if let Some((index, end)) = next_end {
+                let flight = state.in_flight.remove(index);
+                state.now_ns = end;
+                let outcomes = state.outcomes(&flight);
+                state.arrivals.extend(outcomes);
+
+                state.history.push(flight);
+                let oldest = state.in_flight.iter().map(|flight| flight.start_ns).min();
+                let oldest = oldest.unwrap_or(state.now_ns);
+                state.history.retain(|flight| flight.end_ns > oldest);
+            } else if let Some((index, time)) = next_arrival {
+                let (_, outcome) = state.arrivals.remove(index);
+                state.now_ns = time;
+                drop(state);
+
+                match outcome {
+                    Outcome::Receive(chip, transmission, packet) => {
+                        let _ = chip.offer(&transmission, packet);
+                    }
+                    Outcome::Respond(chip) => chip.respond_ranging(),
+                    Outcome::CompleteRanging(chip, result) => chip.complete_ranging(result),
+                }
+            } else {
+                state.now_ns = deadline;
+                return;
+            }
+        }
+    }
+
+    /// Advance the virtual clock until no packet is on air or on its way to a node.
+    pub fn flush(&self) {
+        loop {
+            let state = self.state();
+            let end = state
+                .in_flight
+                .iter()
+                .map(|flight| flight.end_ns)
+                .chain(state.arrivals.iter().map(|(time, _)| *time))
+                .max();
+            let Some(end) = end else {
+                return;
+            };
+            let by = end.saturating_sub(state.now_ns);
+            drop(state);
+            self.advance(Duration::from_nanos(by));
+        }
+    }
+}
+
+/// Put the packets sent by `node` on air.
+pub(super) fn launch(air: &Weak<Mutex<AirState>>, node: usize, transmissions: Vec<Transmission>) {
+    let Some(air) = air.upgrade() else {
+        return;
+    };
+    let mut state = air.lock().unwrap();
+
+    for transmission in transmissions {
+        let chip = &state.nodes[node].chip;
+        let ranging = (transmission.packet_type == Some(PacketType::Ranging)
+            && chip.ranging_initiator())
+        .then(|| chip.ranging_request_address());
+
+        // A chip sends one packet at a time.
+        let busy_until = state
+            .in_flight
+            .iter()
+            .filter(|flight| flight.sender == node)
+            .map(|flight| flight.end_ns)
+            .max();
+        let start_ns = busy_until.unwrap_or(0).max(state.now_ns);
+
+        state.in_flight.push(Flight {
+            sender: node,
+            end_ns: start_ns + airtime_ns(&transmission),
+            transmission,
+            ranging,
+            start_ns,
+        });
+    }
+}
+
+fn lora_bandwidth_hz(raw: u8) -> f64 {
+    match raw {
+        0x0A => 1_625_000.0,
+        0x18 => 812_500.0,
+        0x26 => 406_250.0,
+        _ => 203_125.0,
+    }
+}
+
+/// The SNR below which a packet can not be demodulated.
+fn min_snr_db(transmission: &Transmission) -> f32 {
+    match transmission.packet_type {
+        Some(PacketType::LoRa) | Some(PacketType::Ranging) => {
+            let sf = (transmission.modulation_params[0] >> 4) as f32;
+            10.0 - 2.5 * sf
+        }
+        _ => 10.0,
+    }
+}
+
+/// Approximate time-on-air of `transmission`.
+fn airtime_ns(transmission: &Transmission) -> u64 {
+    let modulation = &transmission.modulation_params;
+    let packet = &transmission.packet_params;
+    let length = transmission.payload.len() as f64;
+
+    let seconds = match transmission.packet_type {
+        Some(PacketType::LoRa) | Some(PacketType::Ranging) => {
+            let sf = (modulation[0] >> 4) as f64;
+            let symbol = 2f64.powf(sf) / lora_bandwidth_hz(modulation[1]);
+            let cr = match modulation[2] {
+                cr @ 1..=4 => cr,
+                5 => 1,
+                6 => 2,
+                _ => 4,
+            } as f64;
+
+            let preamble = (packet[0] & 0x0F) as f64 * 2f64.powi((packet[0] >> 4) as i32);
+            let implicit_header = if packet[1] == 0x80 { 1.0 } else { 0.0 };
+            let crc = if packet[3] == 0x20 { 1.0 } else { 0.0 };
+
+            let bits = 8.0 * length - 4.0 * sf + 28.0 + 16.0 * crc - 20.0 * implicit_header;
+            let payload = 8.0 + ((bits / (4.0 * sf)).ceil() * (cr + 4.0)).max(0.0);
+            (preamble + 4.25 + payload) * symbol
+        }
+        packet_type => {
+            let bitrate = match (packet_type, modulation[0]) {
+                (Some(PacketType::Flrc), 0x45) => 1_300_000.0,
+                (Some(PacketType::Flrc), 0x69) => 1_040_000.0,
+                (Some(PacketType::Flrc), 0x86) => 650_000.0,
+                (Some(PacketType::Flrc), 0xAA) => 520_000.0,
+                (Some(PacketType::Flrc), 0xC7) => 325_000.0,
+                (Some(PacketType::Flrc), _) => 260_000.0,
+                (_, 0x04) => 2_000_000.0,
+                (_, 0x28) => 1_600_000.0,
+                (_, 0x4C | 0x45) => 1_000_000.0,
+                (_, 0x70 | 0x69) => 800_000.0,
+                (_, 0x8D | 0x86) => 500_000.0,
+                (_, 0xB1 | 0xAA) => 400_000.0,
+                (_, 0xCE | 0xC7) => 250_000.0,
+                _ => 125_000.0,
+            };
+            // Preamble, sync word, header and CRC are taken as 8 bytes.
+            (length + 8.0) * 8.0 / bitrate
+        }
+    };
+
+    (seconds * 1e9) as u64
+}
@@ -0,0 +1,79 @@
+use crate::{
+    capture::{self, DEFAULT_GAP, ReplaySpi},
+    ll,
+    test::MockWait,
+};
+
+/// Logic 2 export with NSS on the enable channel.
+const LOGIC2: &str = r#"name,type,start_time,duration,"mosi","miso"
+"SPI","enable",0.100000,2e-08,,
+"SPI","result",0.100001,1e-06,0x80,0x43
+"SPI","result",0.100002,1e-06,0x00,0x43
+"SPI","disable",0.100003,2e-08,,
+"SPI","enable",0.100100,2e-08,,
+"SPI","result",0.100101,1e-06,0x19,0x43
+"SPI","result",0.100102,1e-06,0x01,0x43
+"SPI","result",0.100103,1e-06,0x53,0x43
+"SPI","result",0.100104,1e-06,0x00,0x43
+"SPI","result",0.100105,1e-06,0x00,0xA9
+"SPI","result",0.100106,1e-06,0x00,0xB7
+"SPI","disable",0.100107,2e-08,,
+"#;
+
+/// Logic 2 export without an enable channel, as in `docs/`.
+const LOGIC2_NO_ENABLE: &str = r#"name,type,start_time,duration,"mosi","miso"
+"SPI","result",0.200000,1e-06,0x8B,0x43
+"SPI","result",0.200001,1e-06,0xC0,0x43
+"SPI","result",0.200002,1e-06,0x34,0x43
+"SPI","result",0.200003,1e-06,0x01,0x43
+"SPI","result",0.200200,1e-06,0x15,0x43
+"SPI","result",0.200201,1e-06,0x00,0x43
+"SPI","result",0.200202,1e-06,0x00,0x00
+"SPI","result",0.200203,1e-06,0x00,0x02
+"#;
+
+#[test]
+fn capture_decode() {
+    let frames = capture::parse_csv(LOGIC2, DEFAULT_GAP).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(capture::decode(&frames[0]).to_string(), "SET_STANDBY 00");
+    assert_eq!(
+        capture::decode(&frames[1]).to_string(),
+        "READ_REGISTER 0x0153 (FIRMWARE_VERSIONS) -> A9 B7"
+    );
+
+    let frames = capture::parse_csv(LOGIC2_NO_ENABLE, DEFAULT_GAP).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(
+        capture::decode(&frames[0]).to_string(),
+        "SET_MODULATION_PARAMS C0 34 01"
+    );
+    assert_eq!(
+        capture::decode(&frames[1]).to_string(),
+        "GET_IRQ_STATUS -> 00 02"
+    );
+
+    let source = capture::test_source(&frames);
+    assert!(source.contains("cmd_w(0x8B, &[0xC0, 0x34, 0x01]),"));
+    assert!(source.contains("cmd_r(0x15, &[0x00, 0x02]),"));
+}
+
+#[test]
+fn capture_replay() {
+    let frames = capture::parse_csv(LOGIC2, DEFAULT_GAP).unwrap();
+    let mut ll = ll::Device::new(ll::Interface::new(ReplaySpi::new(frames), MockWait));
+
+    embassy_futures::block_on(async {
+        ll.set_standby()
+            .dispatch_async(|cmd| cmd.set_standby_config(ll::StandbyConfig::StdbyRc))
+            .await
+            .unwrap();
+        let fw = ll.firmware_versions().read_async().await.unwrap().value();
+        assert_eq!(fw, ll::FirmwareVersion::Version2);
+
+        // Diverging from the capture is reported as an SPI error.
+        let error = ll.set_fs().dispatch_async().await.unwrap_err();
+        assert_eq!(error.index, 2);
+        assert_eq!(error.actual, [0xC1]);
+    });
+}
@@ -15,7 +15,8 @@ const fn freq_reference(freq: u32) -> [u8; 3] {
     [(val >> 16) as u8, (val >> 8) as u8, val as u8]
 }
 
-const DEFAULT_PARAMS: hl::lora::LoRaModemParams = hl::lora::LoRaModemParams {
+pub(super) const DEFAULT_PARAMS: hl::lora::LoRaModemParams = hl::lora::LoRaModemParams {
+    regulator: ll::RegulatorType::DcDc,
     frequency: Frequency::new(2_405_000_000),
     tx_params: TxParams {
         power: 22,
@@ -69,7 +70,6 @@ fn capture_configure() {
         reg_r(0x153, &[0xA9, 0xB7]),
         cmd_w(0x80, &[0x00]),
         cmd_w(0x96, &[0x01]),
-        cmd_w(0x80, &[0x00]),
         cmd_w(0x86, &[0xB9, 0x00, 0x00]),
         cmd_w(0x8A, &[0x01]),
         cmd_w(0x8B, &[0xC0, 0x34, 0x01]),
@@ -80,8 +80,10 @@ fn capture_configure() {
         cmd_w(0x8C, &[0x08, 0x00, 0x20, 0x20, 0x40, 0x00, 0x00]),
         cmd_w(0x8E, &[0x1F, 0xE0]),
         cmd_w(0x89, &[0x3F]),
+        cmd_r(0xC0, &[0x43]),
         // After a while
         cmd_w(0x80, &[0x00]),
+        cmd_w(0x96, &[0x01]),
         cmd_w(0x86, &[0xB9, 0x00, 0x00]),
         cmd_w(0x8A, &[0x01]),
         cmd_w(0x8B, &[0xC0, 0x34, 0x01]),
@@ -110,16 +112,6 @@ fn capture_configure() {
 
             let fw = ll.firmware_versions().read_async().await.unwrap().value();
             assert_eq!(fw, ll::FirmwareVersion::Version2);
-
-            ll.set_standby()
-                .dispatch_async(|cmd| cmd.set_standby_config(ll::StandbyConfig::StdbyRc))
-                .await
-                .unwrap();
-
-            ll.set_regulator_mode()
-                .dispatch_async(|cmd| cmd.set_regulator_type(ll::RegulatorType::DcDc))
-                .await
-                .unwrap();
         }
 
         hl.configure().await.unwrap();
@@ -235,3 +227,701 @@ fn capture_rx() {
 
     spi.done();
 }
+
+#[test]
+fn frequency_to_hz() {
+    for freq_hz in [2_400_000_000, 2_405_000_000, 2_440_123_456, 2_500_000_000] {
+        let freq = Frequency::new(freq_hz);
+        assert!(freq_hz - freq.to_hz() < 199);
+        assert_eq!(Frequency::new(freq.to_hz()), freq);
+    }
+
+    assert_eq!(Frequency::checked_new(2_399_999_999), None);
+    assert_eq!(Frequency::checked_new(2_500_000_001), None);
+    assert_eq!(
+        Frequency::checked_new(2_405_000_000),
+        Some(Frequency::new(2_405_000_000))
+    );
+
+    let freq = Frequency::new(2_405_000_000);
+    assert_eq!(
+        freq.checked_offset(1_000_000),
+        Some(Frequency::new(2_406_000_000))
+    );
+    assert_eq!(freq.checked_offset(-6_000_000), None);
+}
+
+#[test]
+fn channel_plan() {
+    let plan = ChannelPlan::new(Frequency::new(2_402_000_000), 2_000_000, 40).unwrap();
+
+    assert_eq!(plan.count(), 40);
+    assert_eq!(plan.channel(0), Some(Frequency::new(2_402_000_000)));
+    assert_eq!(plan.channel(39), Some(Frequency::new(2_480_000_000)));
+    assert_eq!(plan.channel(40), None);
+    assert_eq!(plan.channels().count(), 40);
+
+    assert!(ChannelPlan::new(Frequency::new(2_402_000_000), 2_000_000, 0).is_none());
+    assert!(ChannelPlan::new(Frequency::new(2_480_000_000), 2_000_000, 20).is_none());
+}
+
+#[test]
+fn hop_sequence() {
+    let plan = ChannelPlan::new(Frequency::new(2_402_000_000), 2_000_000, 40).unwrap();
+    let sequence = hopping::HopSequence::new(plan, 0xC0FFEE);
+
+    let mut seen = [false; 40];
+    for position in 0..40 {
+        let index = sequence.channel_index(position);
+        assert!(!seen[index as usize]);
+        seen[index as usize] = true;
+        assert_eq!(sequence.position_of(index), position);
+    }
+    assert_eq!(sequence.channel_index(40), sequence.channel_index(0));
+
+    let other = hopping::HopSequence::new(plan, 0xC0FFEF);
+    assert!((0..40).any(|position| sequence.channel(position) != other.channel(position)));
+}
+
+#[test]
+fn send_hopping() {
+    let plan = ChannelPlan::new(Frequency::new(2_402_000_000), 2_000_000, 40).unwrap();
+    let mut hopper = hopping::Hopper::new(
+        hopping::HopSequence::new(plan, 42),
+        hopping::HopMode::PerPacket,
+    );
+    let channel = hopper.channel();
+
+    let expectations = [
+        cmd_w(0x86, &channel.as_bytes()),
+        cmd_w(0x8F, &[0x00, 0x00]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x04, 0x20, 0x40, 0x00, 0x00]),
+        buf_w(0x00, &[0xAB; 4]),
+        cmd_w(0x8D, &[0x40, 0x41, 0x40, 0x41, 0x00, 0x00, 0x00, 0x00]),
+        cmd_w(0x83, &[0x00, 0x00, 0x00]),
+        cmd_r(0x15, &[0x00, 0x01]),
+        cmd_w(0x97, &[0x00, 0x01]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        hl.send_hopping(&mut hopper, &[0xAB; 4]).await.unwrap();
+    });
+    assert_eq!(hopper.position(), 1);
+
+    spi.done();
+}
+
+#[derive(Default)]
+struct RecordingFrontend {
+    states: Vec<frontend::RfState>,
+}
+
+impl frontend::RfFrontend for RecordingFrontend {
+    fn set_state(&mut self, state: frontend::RfState) {
+        self.states.push(state);
+    }
+
+    fn tx_power_register(&self, power: i8) -> u8 {
+        // An external PA with 20 dB of gain.
+        (power - 20 + 18) as u8
+    }
+}
+
+#[test]
+fn frontend() {
+    let expectations = [
+        cmd_w(0x80, &[0x00]),
+        cmd_w(0x96, &[0x01]),
+        cmd_w(0x86, &[0xB9, 0x00, 0x00]),
+        cmd_w(0x8A, &[0x01]),
+        cmd_w(0x8B, &[0xC0, 0x34, 0x01]),
+        reg_r(0x925, &[0x00]),
+        reg_w(0x925, &[0x32]),
+        reg_w(0x93C, &[0x01]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x20, 0x20, 0x40, 0x00, 0x00]),
+        cmd_w(0x8E, &[0x12, 0xE0]),
+        cmd_w(0x8F, &[0x00, 0x00]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x04, 0x20, 0x40, 0x00, 0x00]),
+        buf_w(0x00, &[0xAB; 4]),
+        cmd_w(0x8D, &[0x40, 0x41, 0x40, 0x41, 0x00, 0x00, 0x00, 0x00]),
+        cmd_w(0x83, &[0x00, 0x00, 0x00]),
+        cmd_r(0x15, &[0x00, 0x01]),
+        cmd_w(0x97, &[0x00, 0x01]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new_with_frontend(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        RecordingFrontend::default(),
+        hl::lora::LoRaModemParams {
+            tx_params: TxParams {
+                power: 20,
+                ramp_time: ll::RampTime::RadioRamp20Us,
+            },
+            ..DEFAULT_PARAMS
+        },
+    );
+
+    embassy_futures::block_on(async {
+        hl.configure().await.unwrap();
+        hl.send(&[0xAB; 4]).await.unwrap();
+    });
+
+    assert_eq!(
+        hl.frontend().states,
+        [
+            frontend::RfState::Standby,
+            frontend::RfState::Tx,
+            frontend::RfState::Standby
+        ]
+    );
+
+    spi.done();
+}
+
+#[test]
+fn switched_frontend_power_table() {
+    use frontend::RfFrontend;
+
+    static TABLE: [(i8, u8); 3] = [(10, 0x05), (20, 0x0F), (27, 0x16)];
+    let frontend = frontend::SwitchedFrontend::new(MockOutput, MockOutput, &TABLE);
+
+    assert_eq!(frontend.tx_power_register(0), 0x05);
+    assert_eq!(frontend.tx_power_register(22), 0x0F);
+    assert_eq!(frontend.tx_power_register(30), 0x16);
+}
+
+#[test]
+fn probe() {
+    let expectations = [
+        reg_r(0x153, &[0xFF, 0xFF]),
+        reg_r(0x153, &[0xA9, 0xB5]),
+        cmd_w(0xA3, &[0x01]),
+        reg_r(0x153, &[0xA9, 0xB7]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        assert_eq!(
+            hl.set_ranging_role(ll::RangingRole::Initiator).await,
+            Err(Error::NotProbed)
+        );
+        assert_eq!(
+            hl.probe(chip::ChipVariant::Sx1280).await,
+            Err(Error::UnknownFirmware(0xFFFF))
+        );
+
+        let chip = hl.probe(chip::ChipVariant::Sx1280).await.unwrap();
+        assert_eq!(chip.firmware, ll::FirmwareVersion::Version1);
+        hl.set_ranging_role(ll::RangingRole::Initiator)
+            .await
+            .unwrap();
+
+        hl.probe(chip::ChipVariant::Sx1281).await.unwrap();
+        assert_eq!(hl.set_advanced_ranging(true).await, Err(Error::Unsupported));
+    });
+
+    spi.done();
+}
+
+#[test]
+fn calibration() {
+    let expectations = [
+        cmd_w(0x89, &[0x05]),
+        cmd_r(0xC0, &[0x54]),
+        cmd_w(0x89, &[0x05]),
+        cmd_r(0xC0, &[0x43]),
+        // Temperature drifted
+        cmd_w(0x89, &[0x05]),
+        cmd_r(0xC0, &[0x43]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        let params = calibration::CalibrationParams::none().rc64k(true).pll(true);
+
+        assert_eq!(
+            hl.calibrate_with(params).await,
+            Err(Error::CalibrationFailed)
+        );
+        hl.calibrate_with(params).await.unwrap();
+
+        assert!(!hl.update_temperature(20).await.unwrap());
+        assert!(!hl.update_temperature(35).await.unwrap());
+        assert!(hl.update_temperature(40).await.unwrap());
+        assert!(!hl.update_temperature(30).await.unwrap());
+    });
+
+    spi.done();
+}
+
+#[test]
+fn sleep_without_retention() {
+    let expectations = [
+        cmd_w(0x84, &[0x00]),
+        wake(),
+        cmd_w(0x80, &[0x00]),
+        cmd_w(0x96, &[0x01]),
+        cmd_w(0x86, &[0xB9, 0x00, 0x00]),
+        cmd_w(0x8A, &[0x01]),
+        cmd_w(0x8B, &[0xC0, 0x34, 0x01]),
+        reg_r(0x925, &[0x00]),
+        reg_w(0x925, &[0x32]),
+        reg_w(0x93C, &[0x01]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x20, 0x20, 0x40, 0x00, 0x00]),
+        cmd_w(0x8E, &[0x1F, 0xE0]),
+        cmd_w(0x89, &[0x3F]),
+        cmd_r(0xC0, &[0x43]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        hl.sleep(calibration::SleepParams::default()).await.unwrap();
+        hl.wake().await.unwrap();
+    });
+
+    spi.done();
+}
+
+#[test]
+fn rx_gain() {
+    let expectations = [
+        reg_r(0x891, &[0x25]),
+        reg_w(0x891, &[0xE5]),
+        reg_r(0x895, &[0x01]),
+        reg_w(0x895, &[0x00]),
+        reg_r(0x89E, &[0x0A]),
+        reg_w(0x89E, &[0x07]),
+        reg_r(0x89F, &[0x4D]),
+        reg_w(0x89F, &[0xCD]),
+        // Restore AGC
+        reg_r(0x891, &[0xE5]),
+        reg_w(0x891, &[0x25]),
+        reg_r(0x89F, &[0xCD]),
+        reg_w(0x89F, &[0x4D]),
+        reg_r(0x895, &[0x00]),
+        reg_w(0x895, &[0x01]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        hl.set_rx_gain(gain::RxGainParams {
+            lna_mode: gain::LnaMode::HighSensitivity,
+            gain_control: gain::GainControl::Manual(ll::GainValue::MaxNeg18),
+        })
+        .await
+        .unwrap();
+
+        hl.set_rx_gain(gain::RxGainParams::default()).await.unwrap();
+    });
+
+    spi.done();
+}
+
+#[test]
+fn continuous_wave() {
+    let expectations = [
+        cmd_w(0x80, &[0x00]),
+        cmd_w(0x86, &Frequency::new(2_440_000_000).as_bytes()),
+        cmd_w(0x8E, &[0x12, 0x20]),
+        cmd(0xD1),
+        cmd_w(0x80, &[0x00]),
+        cmd_w(0x86, &[0xB9, 0x00, 0x00]),
+        cmd_w(0x8E, &[0x1F, 0xE0]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        hl.start_continuous_wave(
+            Frequency::new(2_440_000_000),
+            TxParams {
+                power: 0,
+                ramp_time: ll::RampTime::RadioRamp04Us,
+            },
+        )
+        .await
+        .unwrap();
+        hl.stop_test_mode().await.unwrap();
+    });
+
+    spi.done();
+}
+
+#[test]
+fn scan() {
+    let plan = ChannelPlan::new(Frequency::new(2_402_000_000), 2_000_000, 2).unwrap();
+    let expectations = [
+        cmd_w(0x80, &[0x00]),
+        cmd_w(0x86, &plan.channel(0).unwrap().as_bytes()),
+        cmd_w(0x82, &[0x00, 0xFF, 0xFF]),
+        cmd_r(0x1F, &[0xC0]),
+        cmd_r(0x1F, &[0xC4]),
+        cmd_w(0x80, &[0x00]),
+        cmd_w(0x86, &plan.channel(1).unwrap().as_bytes()),
+        cmd_w(0x82, &[0x00, 0xFF, 0xFF]),
+        cmd_r(0x1F, &[0xA0]),
+        cmd_r(0x1F, &[0xA0]),
+        cmd_w(0x80, &[0x00]),
+        cmd_w(0x86, &[0xB9, 0x00, 0x00]),
+        cmd_w(0x97, &[0xFF, 0xFF]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        let mut table = [0i16; 2];
+        hl.scan(
+            &plan,
+            scan::ScanParams {
+                samples: 2,
+                ..Default::default()
+            },
+            &mut table,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(table, [-97, -80]);
+    });
+
+    spi.done();
+}
+
+#[test]
+fn receive_with_ack() {
+    let expectations = [
+        cmd_w(0x8F, &[0x80, 0x00]),
+        buf_w(0x80, &[0xAC, 0x4B]),
+        cmd_w(0x8D, &[0x40, 0x63, 0x40, 0x63, 0x00, 0x00, 0x00, 0x00]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x02, 0x20, 0x40, 0x00, 0x00]),
+        cmd_w(0x98, &[0x00, 0x64]),
+        cmd_w(0x82, &[0x00, 0x00, 0x00]),
+        cmd_r(0x15, &[0x00, 0x02]),
+        cmd_r(0x1D, &[0x6A, 0x15, 0x32, 0x00, 0x00]),
+        cmd_r(0x17, &[0x04, 0x00]),
+        cmd_w(0x97, &[0x00, 0x02]),
+        // Acknowledgement sent
+        cmd_r(0x15, &[0x00, 0x01]),
+        cmd_w(0x97, &[0x00, 0x01]),
+        cmd_w(0x98, &[0x00, 0x00]),
+        buf_r(0x00, &[0x01, 0x02, 0x03, 0x04]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        let mut buf = [0u8; 32];
+        let (len, _) = hl
+            .receive_with_ack(&mut buf, &[0xAC, 0x4B], 100)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf[..len], [0x01, 0x02, 0x03, 0x04]);
+    });
+
+    spi.done();
+}
+
+#[test]
+fn receive_with_ack_implicit() {
+    let expectations = [
+        cmd_w(0x8F, &[0x80, 0x00]),
+        buf_w(0x80, &[0xAC]),
+        buf_w(0x81, &[0x00, 0x00, 0x00]),
+        cmd_w(0x8D, &[0x40, 0x63, 0x40, 0x63, 0x00, 0x00, 0x00, 0x00]),
+        cmd_w(0x8C, &[0x08, 0x80, 0x04, 0x20, 0x40, 0x00, 0x00]),
+        cmd_w(0x98, &[0x00, 0x64]),
+        cmd_w(0x82, &[0x00, 0x00, 0x00]),
+        cmd_r(0x15, &[0x00, 0x02]),
+        cmd_r(0x1D, &[0x6A, 0x15, 0x32, 0x00, 0x00]),
+        cmd_r(0x17, &[0x04, 0x00]),
+        cmd_w(0x97, &[0x00, 0x02]),
+        // Acknowledgement sent
+        cmd_r(0x15, &[0x00, 0x01]),
+        cmd_w(0x97, &[0x00, 0x01]),
+        cmd_w(0x98, &[0x00, 0x00]),
+        buf_r(0x00, &[0x01, 0x02, 0x03, 0x04]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut params = DEFAULT_PARAMS;
+    params.packet_params.header_type = LoRaHeader::Implicit;
+    let mut hl = hl::SX128X::new(&mut spi, MockWait, MockWait, MockOutput, MockDelay, params);
+
+    embassy_futures::block_on(async {
+        let mut buf = [0u8; 4];
+        let (len, _) = hl
+            .receive_with_ack(&mut buf, &[0xAC], 100)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf[..len], [0x01, 0x02, 0x03, 0x04]);
+    });
+
+    spi.done();
+}
+
+#[test]
+fn receive_with_ack_too_long() {
+    let expectations = [
+        cmd_w(0x8F, &[0x80, 0x00]),
+        buf_w(0x80, &[0xAC]),
+        cmd_w(0x8D, &[0x40, 0x63, 0x40, 0x63, 0x00, 0x00, 0x00, 0x00]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x01, 0x20, 0x40, 0x00, 0x00]),
+        cmd_w(0x98, &[0x00, 0x64]),
+        cmd_w(0x82, &[0x00, 0x00, 0x00]),
+        cmd_r(0x15, &[0x00, 0x02]),
+        cmd_r(0x1D, &[0x6A, 0x15, 0x32, 0x00, 0x00]),
+        cmd_r(0x17, &[0x81, 0x00]),
+        cmd_w(0x97, &[0x00, 0x02]),
+        // Acknowledgement sent
+        cmd_r(0x15, &[0x00, 0x01]),
+        cmd_w(0x97, &[0x00, 0x01]),
+        cmd_w(0x98, &[0x00, 0x00]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        let mut buf = [0u8; 255];
+        // Neither the acknowledgement nor an implicit header packet may reach past the buffer half.
+        assert_eq!(
+            hl.receive_with_ack(&mut buf, &[0xAC; 129], 100).await,
+            Err(Error::TooLong)
+        );
+        // A longer packet in explicit header mode is only noticed afterwards.
+        assert_eq!(
+            hl.receive_with_ack(&mut buf, &[0xAC], 100).await,
+            Err(Error::TooLong)
+        );
+    });
+
+    spi.done();
+
+    let mut spi = Mock::new(&[]);
+    let mut params = DEFAULT_PARAMS;
+    params.packet_params.header_type = LoRaHeader::Implicit;
+    let mut hl = hl::SX128X::new(&mut spi, MockWait, MockWait, MockOutput, MockDelay, params);
+
+    embassy_futures::block_on(async {
+        assert_eq!(
+            hl.receive_with_ack(&mut [0u8; 129], &[0xAC], 100).await,
+            Err(Error::TooLong)
+        );
+        assert_eq!(
+            hl.receive_with_ack(&mut [0u8; 2], &[0xAC; 3], 100).await,
+            Err(Error::TooLong)
+        );
+    });
+
+    spi.done();
+}
+
+#[test]
+fn auto_fs() {
+    let expectations = [
+        cmd_w(0x80, &[0x01]),
+        cmd_w(0x9E, &[0x01]),
+        cmd(0xC1),
+        cmd_w(0x8F, &[0x00, 0x00]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x04, 0x20, 0x40, 0x00, 0x00]),
+        buf_w(0x00, &[0xAB; 4]),
+        cmd_w(0x8D, &[0x40, 0x41, 0x40, 0x41, 0x00, 0x00, 0x00, 0x00]),
+        cmd_w(0x83, &[0x00, 0x00, 0x00]),
+        cmd_r(0x15, &[0x00, 0x01]),
+        cmd_w(0x97, &[0x00, 0x01]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new_with_frontend(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        RecordingFrontend::default(),
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        hl.standby(ll::StandbyConfig::StdbyXosc).await.unwrap();
+        hl.set_auto_fs(true).await.unwrap();
+        hl.set_fs().await.unwrap();
+        hl.send(&[0xAB; 4]).await.unwrap();
+    });
+
+    assert_eq!(
+        hl.frontend().states,
+        [
+            frontend::RfState::Standby,
+            frontend::RfState::Fs,
+            frontend::RfState::Tx,
+            frontend::RfState::Fs
+        ]
+    );
+
+    spi.done();
+}
+
+#[test]
+fn receive_duty_cycled() {
+    let expectations = [
+        cmd_w(0x9B, &[0x01]),
+        cmd_w(0x8F, &[0x00, 0x00]),
+        cmd_w(0x8D, &[0x40, 0x62, 0x40, 0x62, 0x00, 0x00, 0x00, 0x00]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x20, 0x20, 0x40, 0x00, 0x00]),
+        cmd_w(0x94, &[0x02, 0x00, 0x0A, 0x03, 0xE8]),
+        cmd_r(0x15, &[0x00, 0x02]),
+        cmd_r(0x1D, &[0x6A, 0x15, 0x32, 0x00, 0x00]),
+        cmd_r(0x17, &[0x02, 0x00]),
+        buf_r(0x00, &[0x12, 0x34]),
+        cmd_w(0x97, &[0x00, 0x02]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut hl = hl::SX128X::new(
+        &mut spi,
+        MockWait,
+        MockWait,
+        MockOutput,
+        MockDelay,
+        DEFAULT_PARAMS,
+    );
+
+    embassy_futures::block_on(async {
+        let mut buf = [0u8; 32];
+        hl.set_long_preamble(true).await.unwrap();
+        let (len, _) = hl
+            .receive_duty_cycled(
+                &mut buf,
+                duty_cycle::DutyCycleParams {
+                    period_base: ll::RxTimeoutStep::Step1Ms,
+                    rx_count: 10,
+                    sleep_count: 1000,
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf[..len], [0x12, 0x34]);
+    });
+
+    spi.done();
+}
+
+#[test]
+fn save_context() {
+    let expectations = [
+        cmd(0xD5),
+        cmd_w(0x84, &[0x01]),
+        // After a host reset
+        wake(),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+
+    embassy_futures::block_on(async {
+        let context = {
+            let mut hl = hl::SX128X::new(
+                &mut spi,
+                MockWait,
+                MockWait,
+                MockOutput,
+                MockDelay,
+                DEFAULT_PARAMS,
+            );
+            let context = hl.save_context().await.unwrap();
+            hl.sleep(calibration::SleepParams {
+                ram_retention: true,
+                buffer_retention: false,
+            })
+            .await
+            .unwrap();
+            context
+        };
+
+        let mut hl = hl::SX128X::new(
+            &mut spi,
+            MockWait,
+            MockWait,
+            MockOutput,
+            MockDelay,
+            Default::default(),
+        );
+        hl.restore_context(context);
+        hl.wake().await.unwrap();
+
+        assert_eq!(hl.context().params, DEFAULT_PARAMS);
+    });
+
+    spi.done();
+}
@@ -180,8 +180,8 @@ fn capture_tx() {
             .await
             .unwrap();
         {
-            let mut buf = [0x00; 16];
-            ll.buffer().write_all_async(&mut buf).await.unwrap();
+            let buf = [0x00; 16];
+            ll.buffer().write_all_async(&buf).await.unwrap();
         }
 
         ll.set_dio_irq_params()
@@ -406,3 +406,96 @@ fn capture_rx() {
 
     spi.done();
 }
+
+/// The typed LoRa variants encode the same bytes as the raw values from the configure capture.
+#[test]
+fn typed_lora_params() {
+    let expectations = [
+        cmd_w(0x8B, &[0xC0, 0x34, 0x01]),
+        cmd_w(0x8C, &[0x08, 0x00, 0x20, 0x20, 0x40, 0x00, 0x00]),
+        cmd_r(0x1D, &[0x40, 0xF8, 0x00, 0x00, 0x00]),
+        cmd_r(0x03, &[0x01]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut ll = ll::Device::new(ll::Interface::new(&mut spi, MockWait));
+
+    embassy_futures::block_on(async {
+        ll.set_modulation_params_lora()
+            .dispatch_async(|cmd| {
+                cmd.set_spreading_factor(ll::LoraSf::Sf12);
+                cmd.set_bandwidth(ll::LoraBw::Bw200);
+                cmd.set_coding_rate(ll::LoraCr::Cr45);
+            })
+            .await
+            .unwrap();
+
+        ll.set_packet_params_lora()
+            .dispatch_async(|cmd| {
+                cmd.set_preamble_mantissa(8);
+                cmd.set_preamble_exponent(0);
+                cmd.set_header_type(ll::LoraHeaderType::Explicit);
+                cmd.set_payload_length(0x20);
+                cmd.set_crc(ll::LoraCrc::Enabled);
+                cmd.set_invert_iq(ll::LoraIq::Standard);
+            })
+            .await
+            .unwrap();
+
+        let status = ll.get_packet_status_lora().dispatch_async().await.unwrap();
+        assert_eq!(status.rssi_sync(), 0x40);
+        assert_eq!(status.snr(), -8);
+
+        let packet_type = ll.get_packet_type().dispatch_async().await.unwrap();
+        assert_eq!(packet_type.value(), Ok(ll::PacketType::LoRa));
+    });
+
+    spi.done();
+}
+
+#[test]
+fn ranging_result() {
+    let expectations = [
+        reg_r(0x924, &[0x03]),
+        reg_w(0x924, &[0x13]),
+        reg_w(0x97F, &[0x02]),
+        reg_r(0x961, &[0x00, 0x12, 0x34]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut ll = ll::Device::new(ll::Interface::new(&mut spi, MockWait));
+
+    embassy_futures::block_on(async {
+        ll.ranging_result_mux()
+            .modify_async(|reg| reg.set_mux(ll::RangingResultType::Averaged))
+            .await
+            .unwrap();
+        ll.freeze_ranging_result()
+            .write_async(|reg| reg.set_freeze(true))
+            .await
+            .unwrap();
+
+        let result = ll.ranging_result().read_async().await.unwrap();
+        assert_eq!(result.value(), 0x1234);
+    });
+
+    spi.done();
+}
+
+#[test]
+fn frequency_error() {
+    let expectations = [
+        reg_r(0x954, &[0x0F, 0xFF, 0xFE]),
+        reg_r(0x954, &[0x00, 0x01, 0x00]),
+        // The header CRC flag sits just above the frequency error.
+        reg_r(0x954, &[0x1F]),
+    ];
+    let mut spi = Mock::new(expectations.iter().flatten());
+    let mut ll = ll::Device::new(ll::Interface::new(&mut spi, MockWait));
+
+    embassy_futures::block_on(async {
+        assert_eq!(ll.fei().read_async().await.unwrap().fei(), -2);
+        assert_eq!(ll.fei().read_async().await.unwrap().fei(), 0x100);
+        assert!(ll.header_crc().read_async().await.unwrap().crc_present());
+    });
+
+    spi.done();
+}
@@ -4,8 +4,10 @@ use embedded_hal::digital::{ErrorType, OutputPin};
 use embedded_hal_async::{delay::DelayNs, digital::Wait};
 use embedded_hal_mock::eh1::spi::Transaction;
 
+mod capture;
 mod hl;
 mod ll;
+mod sim;
 
 fn cmd_g(cmd: u8, in_array: &[u8], out_array: &[u8]) -> Vec<Transaction<u8>> {
     vec![
@@ -17,6 +19,24 @@ fn cmd_g(cmd: u8, in_array: &[u8], out_array: &[u8]) -> Vec<Transaction<u8>> {
     ]
 }
 
+fn cmd(cmd: u8) -> Vec<Transaction<u8>> {
+    vec![
+        Transaction::transaction_start(),
+        Transaction::write(cmd),
+        Transaction::transaction_end(),
+    ]
+}
+
+/// The `GET_STATUS` used to wake the chip, which does not read back the status.
+fn wake() -> Vec<Transaction<u8>> {
+    vec![
+        Transaction::transaction_start(),
+        Transaction::write(0xC0),
+        Transaction::write_vec(vec![0x00]),
+        Transaction::transaction_end(),
+    ]
+}
+
 fn cmd_w(cmd: u8, in_array: &[u8]) -> Vec<Transaction<u8>> {
     cmd_g(cmd, in_array, &[])
 }
@@ -114,3 +134,41 @@ struct MockDelay;
 impl DelayNs for MockDelay {
     async fn delay_ns(&mut self, _ns: u32) {}
 }
+
+/// Run `f` and return the lines it logged on the current thread.
+#[cfg(feature = "log")]
+fn logged(f: impl FnOnce()) -> Vec<String> {
+    use std::{
+        sync::Mutex,
+        thread::{self, ThreadId},
+    };
+
+    static LINES: Mutex<Vec<(ThreadId, String)>> = Mutex::new(Vec::new());
+
+    struct Recorder;
+
+    impl log::Log for Recorder {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let line = record.args().to_string();
+            LINES.lock().unwrap().push((thread::current().id(), line));
+        }
+
+        fn flush(&self) {}
+    }
+
+    // Tests run in parallel threads, sharing the one logger.
+    let _ = log::set_logger(&Recorder);
+    log::set_max_level(log::LevelFilter::Trace);
+
+    f();
+
+    let id = thread::current().id();
+    let mut lines = LINES.lock().unwrap();
+    let (own, others) = lines.drain(..).partition(|(thread, _)| *thread == id);
+    *lines = others;
+    own.into_iter().map(|(_, line)| line).collect()
+}
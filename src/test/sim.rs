@@ -0,0 +1,718 @@
+use core::time::Duration;
+
+use embassy_futures::{
+    join::join,
+    select::{Either, select},
+    yield_now,
+};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_hal_async::delay::DelayNs;
+
+use crate::{
+    hl::{
+        self, ChannelPlan, Frequency, RxTimeout, TxParams,
+        buffer::BufferBases,
+        calibration::SleepParams,
+        chip::ChipVariant,
+        hopping::{HopMode, HopSequence, Hopper},
+        irq::Irq,
+        multi::Radios,
+        preempt::RxOutcome,
+        scan::ScanParams,
+        task::{self, RadioChannels, TxPacket},
+    },
+    ll,
+    sim::{
+        Incoming, Mode, SimChip,
+        air::{Air, AirParams},
+    },
+    test::hl::DEFAULT_PARAMS,
+};
+
+#[test]
+fn sim_send() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+
+    embassy_futures::block_on(async {
+        radio.reset().await;
+        let info = radio.probe(ChipVariant::Sx1280).await.unwrap();
+        assert_eq!(info.firmware, ll::FirmwareVersion::Version2);
+
+        radio.configure().await.unwrap();
+        radio.send(&[1, 2, 3, 4]).await.unwrap();
+    });
+
+    let sent = chip.take_transmitted();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].payload, [1, 2, 3, 4]);
+    assert_eq!(sent[0].frequency, DEFAULT_PARAMS.frequency);
+    assert_eq!(sent[0].packet_type, Some(ll::PacketType::LoRa));
+    assert_eq!(chip.mode(), Mode::StandbyRc);
+    assert!(chip.irq_status().is_empty());
+}
+
+#[test]
+#[cfg(feature = "log")]
+fn sim_traced() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+    radio.set_verbosity(ll::Verbosity::Fields);
+
+    let lines = crate::test::logged(|| {
+        embassy_futures::block_on(async {
+            radio.reset().await;
+            radio.probe(ChipVariant::Sx1280).await.unwrap();
+            radio.configure().await.unwrap();
+            radio.send(&[1, 2, 3, 4]).await.unwrap();
+        })
+    });
+
+    assert_eq!(chip.take_transmitted()[0].payload, [1, 2, 3, 4]);
+
+    // Each access is named, and followed by its decoded fields.
+    let decoded = |access: &str| {
+        let index = lines.iter().position(|line| line.starts_with(access))?;
+        lines.get(index + 1).map(String::as_str)
+    };
+    assert_eq!(
+        decoded("READ_REGISTER FIRMWARE_VERSIONS"),
+        Some("  FirmwareVersions { value: Version2 }")
+    );
+    assert_eq!(
+        decoded("SET_PACKET_TYPE"),
+        Some("  SetPacketTypeFieldsIn { value: Ok(LoRa) }")
+    );
+    assert_eq!(
+        decoded("SET_TX_PARAMS"),
+        Some("  SetTxParamsFieldsIn { power: 31, ramp_time: RadioRamp20Us }")
+    );
+    assert_eq!(
+        decoded("SET_TX "),
+        Some("  SetTxFieldsIn { period_base: Step15Us625, period_base_count: SingleMode }")
+    );
+    assert!(lines.iter().any(|line| line.starts_with("WRITE_BUFFER @0")));
+}
+
+#[test]
+fn sim_receive() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+
+    chip.deliver(Incoming {
+        rssi_dbm: -80,
+        ..Incoming::new(b"hello")
+    });
+    chip.deliver(Incoming {
+        crc_error: true,
+        ..Incoming::new(b"corrupt")
+    });
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+
+        let mut buf = [0; 32];
+        let (len, status) = radio.receive(&mut buf).await.unwrap().unwrap();
+        assert_eq!(&buf[..len], b"hello");
+        assert_eq!(status.rssi_sync, 160);
+
+        assert_eq!(radio.receive(&mut buf).await.unwrap(), None);
+    });
+
+    assert!(chip.irq_status().is_empty());
+}
+
+#[test]
+fn sim_receive_in_place() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+
+    chip.deliver(Incoming::new(b"head:body"));
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+        radio.set_buffer_bases(BufferBases { tx: 0x80, rx: 0xFC });
+
+        let received = radio.receive_in_place(64).await.unwrap().unwrap();
+        assert_eq!((received.offset, received.len), (0xFC, 9));
+
+        // The body wraps around the end of the buffer.
+        let mut header = [0; 4];
+        radio
+            .read_buffer(received.offset_of(0), &mut header)
+            .await
+            .unwrap();
+        assert_eq!(&header, b"head");
+        let mut body = [0; 4];
+        radio
+            .read_buffer(received.offset_of(5), &mut body)
+            .await
+            .unwrap();
+        assert_eq!(&body, b"body");
+
+        // The reply does not overwrite the received packet.
+        radio.send(b"reply").await.unwrap();
+        let mut packet = [0; 9];
+        radio
+            .read_buffer(received.offset, &mut packet)
+            .await
+            .unwrap();
+        assert_eq!(&packet, b"head:body");
+    });
+
+    assert_eq!(chip.take_transmitted()[0].payload, b"reply");
+}
+
+#[test]
+fn sim_transmit_loaded() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+        assert_eq!(radio.transmit_loaded().await, Err(hl::Error::NothingLoaded));
+        assert_eq!(radio.load_tx(&[0; 256]).await, Err(hl::Error::TooLong));
+
+        radio.set_buffer_bases(BufferBases { tx: 0x80, rx: 0x00 });
+        radio.load_tx(b"beacon").await.unwrap();
+        radio.transmit_loaded().await.unwrap();
+
+        // Receiving below the TX base keeps the payload, but changes the payload length.
+        chip.deliver(Incoming::new(b"a longer packet"));
+        let mut buf = [0; 32];
+        radio.receive(&mut buf).await.unwrap().unwrap();
+        radio.transmit_loaded().await.unwrap();
+
+        // A packet received at the TX base overwrites it.
+        radio.set_buffer_bases(BufferBases { tx: 0x00, rx: 0x00 });
+        radio.load_tx(b"beacon").await.unwrap();
+        chip.deliver(Incoming::new(b"overwrite"));
+        radio.receive(&mut buf).await.unwrap().unwrap();
+        assert_eq!(radio.transmit_loaded().await, Err(hl::Error::NothingLoaded));
+
+        // So does a packet dropped for its CRC error.
+        radio.load_tx(b"beacon").await.unwrap();
+        chip.deliver(Incoming {
+            crc_error: true,
+            ..Incoming::new(b"corrupt")
+        });
+        assert_eq!(radio.receive(&mut buf).await.unwrap(), None);
+        assert_eq!(radio.transmit_loaded().await, Err(hl::Error::NothingLoaded));
+    });
+
+    let sent = chip.take_transmitted();
+    assert_eq!(sent.len(), 2);
+    assert!(
+        sent.iter()
+            .all(|transmission| transmission.payload == b"beacon")
+    );
+}
+
+const HOP_TIMEOUT: RxTimeout = RxTimeout {
+    period_base: ll::RxTimeoutStep::Step1Ms,
+    count: 100,
+};
+
+fn hopper() -> Hopper {
+    let plan = ChannelPlan::new(Frequency::new(2_402_000_000), 2_000_000, 40).unwrap();
+    Hopper::new(HopSequence::new(plan, 42), HopMode::PerPacket)
+}
+
+/// Let the timed RX started by `operation` expire.
+async fn expiring<T>(chip: &SimChip, operation: impl Future<Output = T>) -> T {
+    let expire = async {
+        while chip.mode() != Mode::Rx {
+            yield_now().await;
+        }
+        chip.expire_timeout();
+    };
+    join(operation, expire).await.0
+}
+
+#[test]
+fn sim_receive_hopping() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+    let mut hopper = hopper();
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+        let mut buf = [0; 32];
+
+        let channel = hopper.channel();
+        chip.deliver(Incoming::new(b"hop"));
+        let (len, _) = radio
+            .receive_hopping(&mut hopper, &mut buf, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf[..len], b"hop");
+        assert_eq!(chip.frequency(), channel);
+        assert_eq!(hopper.position(), 1);
+
+        // The transmitter hops regardless, so a timeout moves on as well.
+        let channel = hopper.channel();
+        let result = expiring(
+            &chip,
+            radio.receive_hopping(&mut hopper, &mut buf, Some(HOP_TIMEOUT)),
+        )
+        .await;
+        assert_eq!(result.unwrap(), None);
+        assert_eq!(chip.frequency(), channel);
+        assert_eq!(hopper.position(), 2);
+    });
+}
+
+#[test]
+fn sim_resync_hopping() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+    let mut hopper = hopper();
+    let parked = hopper.sequence().position_of(7);
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+        let mut buf = [0; 32];
+
+        // Without a packet the hopper stays parked, so the caller can listen again.
+        let result = expiring(
+            &chip,
+            radio.resync_hopping(&mut hopper, 7, &mut buf, HOP_TIMEOUT),
+        )
+        .await;
+        assert_eq!(result.unwrap(), None);
+        assert_eq!(hopper.position(), parked);
+        assert_eq!(chip.frequency(), hopper.channel());
+
+        chip.deliver(Incoming::new(b"sync"));
+        let result = radio
+            .resync_hopping(&mut hopper, 7, &mut buf, HOP_TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(result.map(|(len, _)| len), Some(4));
+        assert_eq!(hopper.position(), (parked + 1) % 40);
+    });
+}
+
+#[test]
+fn sim_sleep_without_retention() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+        radio
+            .set_frequency(hl::Frequency::new(2_450_000_000))
+            .await
+            .unwrap();
+        radio.set_auto_fs(true).await.unwrap();
+
+        radio.sleep(SleepParams::default()).await.unwrap();
+        assert_eq!(chip.mode(), Mode::Sleep);
+
+        radio.wake().await.unwrap();
+        // Waking without retention loses the configuration, which the driver restores.
+        assert_eq!(chip.mode(), Mode::StandbyRc);
+        assert_eq!(chip.frequency(), hl::Frequency::new(2_450_000_000));
+        assert_eq!(chip.packet_type(), Some(ll::PacketType::LoRa));
+
+        radio.send(&[1]).await.unwrap();
+        assert_eq!(chip.mode(), Mode::Fs);
+    });
+}
+
+#[test]
+fn sim_preempted_receive() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+
+        let mut buf = [0; 32];
+        let outcome = radio
+            .receive_preemptible(&mut buf, async { b"request" })
+            .await
+            .unwrap();
+        assert_eq!(outcome, RxOutcome::Preempted(b"request"));
+        assert_eq!(chip.mode(), Mode::StandbyRc);
+        assert!(chip.irq_status().is_empty());
+
+        radio.send(b"request").await.unwrap();
+
+        chip.deliver(Incoming::new(b"response"));
+        let outcome = radio
+            .receive_preemptible(&mut buf, core::future::pending::<()>())
+            .await
+            .unwrap();
+        assert!(matches!(outcome, RxOutcome::Packet(8, _)));
+        assert_eq!(&buf[..8], b"response");
+    });
+
+    assert_eq!(chip.take_transmitted()[0].payload, b"request");
+}
+
+#[test]
+fn sim_cancelled_receive() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+
+        let mut buf = [0; 32];
+        let timeout = async {};
+        assert!(matches!(
+            select(radio.receive(&mut buf), timeout).await,
+            Either::Second(())
+        ));
+        assert_eq!(chip.mode(), Mode::Rx);
+
+        // The next operation first brings the chip back to standby.
+        radio.send(&[1, 2, 3]).await.unwrap();
+        assert_eq!(chip.mode(), Mode::StandbyRc);
+        assert!(chip.irq_status().is_empty());
+
+        chip.deliver(Incoming::new(b"late"));
+        assert_eq!(chip.mode(), Mode::StandbyRc);
+
+        // So does entering standby, while sampling the RSSI keeps listening.
+        radio.listen(32).await.unwrap();
+        radio.rssi_inst().await.unwrap();
+        assert_eq!(chip.mode(), Mode::Rx);
+        radio.standby(ll::StandbyConfig::StdbyXosc).await.unwrap();
+        assert_eq!(chip.mode(), Mode::StandbyXosc);
+        assert!(chip.irq_status().is_empty());
+    });
+
+    assert_eq!(chip.take_transmitted()[0].payload, [1, 2, 3]);
+}
+
+#[test]
+fn sim_unstopped_test_mode() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+    let test_params = TxParams {
+        power: -18,
+        ramp_time: ll::RampTime::RadioRamp02Us,
+    };
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+        radio.send(&[1]).await.unwrap();
+
+        radio
+            .start_continuous_wave(Frequency::new(2_480_000_000), test_params)
+            .await
+            .unwrap();
+        assert_eq!(chip.mode(), Mode::Tx);
+        radio.stop_test_mode().await.unwrap();
+        assert_eq!(chip.mode(), Mode::StandbyRc);
+        radio.send(&[2]).await.unwrap();
+
+        // Without stop_test_mode the next operation restores the modem configuration.
+        radio
+            .start_continuous_wave(Frequency::new(2_480_000_000), test_params)
+            .await
+            .unwrap();
+        radio.send(&[3]).await.unwrap();
+    });
+
+    let sent = chip.take_transmitted();
+    assert_eq!(sent.len(), 3);
+    for transmission in &sent {
+        assert_eq!(transmission.frequency, DEFAULT_PARAMS.frequency);
+        assert_eq!(transmission.power_dbm, sent[0].power_dbm);
+    }
+}
+
+/// A delay that lets other futures run, so that an operation can be cancelled while it waits.
+struct YieldingDelay;
+
+impl DelayNs for YieldingDelay {
+    async fn delay_ns(&mut self, _ns: u32) {
+        yield_now().await;
+    }
+}
+
+#[test]
+fn sim_cancelled_scan() {
+    let chip = SimChip::new();
+    let mut radio = hl::SX128X::new(
+        chip.spi(),
+        chip.busy(),
+        chip.dio1(),
+        chip.nreset(),
+        YieldingDelay,
+        DEFAULT_PARAMS,
+    );
+    let plan = ChannelPlan::new(Frequency::new(2_402_000_000), 2_000_000, 40).unwrap();
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+
+        let mut table = [0; 40];
+        let timeout = async {
+            for _ in 0..10 {
+                yield_now().await;
+            }
+        };
+        assert!(matches!(
+            select(
+                radio.scan(&plan, ScanParams::default(), &mut table),
+                timeout
+            )
+            .await,
+            Either::Second(())
+        ));
+        assert_ne!(chip.frequency(), DEFAULT_PARAMS.frequency);
+
+        // The next operation returns to the configured channel.
+        radio.send(&[1]).await.unwrap();
+    });
+
+    assert_eq!(
+        chip.take_transmitted()[0].frequency,
+        DEFAULT_PARAMS.frequency
+    );
+}
+
+#[test]
+fn sim_radio_task() {
+    let chip = SimChip::new();
+    let mut radio = chip.radio(DEFAULT_PARAMS);
+    let channels = RadioChannels::<NoopRawMutex, 32, 2, 2>::new();
+    assert_eq!(TxPacket::<32>::new(&[0; 33]), None);
+
+    embassy_futures::block_on(async {
+        radio.configure().await.unwrap();
+
+        let application = async {
+            chip.deliver(Incoming::new(b"ping"));
+            let packet = channels.rx.receive().await;
+            assert_eq!(packet.payload(), b"ping");
+
+            channels.tx.send(TxPacket::new(b"pong").unwrap()).await;
+            loop {
+                let sent = chip.take_transmitted();
+                if let Some(transmission) = sent.first() {
+                    assert_eq!(transmission.payload, b"pong");
+                    break;
+                }
+                yield_now().await;
+            }
+        };
+        let radio = task::run(&mut radio, channels.tx.receiver(), channels.rx.sender());
+        assert!(matches!(
+            select(radio, application).await,
+            Either::Second(())
+        ));
+    });
+}
+
+#[test]
+fn sim_multi_radio() {
+    let chips = [SimChip::new(), SimChip::new()];
+    let mut radios = Radios::new(chips.each_ref().map(|chip| chip.radio(DEFAULT_PARAMS)));
+
+    embassy_futures::block_on(async {
+        for index in 0..2 {
+            radios.radio(index).configure().await.unwrap();
+        }
+        radios.listen(32).await.unwrap();
+
+        chips[1].deliver(Incoming::new(b"one"));
+        chips[0].deliver(Incoming::new(b"zero"));
+
+        let mut buf = [0; 32];
+        let packet = radios.receive(&mut buf).await.unwrap();
+        assert_eq!((packet.radio, &buf[..packet.len]), (0, &b"zero"[..]));
+        let packet = radios.receive(&mut buf).await.unwrap();
+        assert_eq!((packet.radio, &buf[..packet.len]), (1, &b"one"[..]));
+
+        radios.send(1, b"ack").await.unwrap();
+    });
+
+    assert_eq!(chips[1].take_transmitted()[0].payload, b"ack");
+    assert_eq!(chips[0].mode(), Mode::Rx);
+    assert_eq!(chips[1].mode(), Mode::Rx);
+}
+
+#[test]
+fn air_delivery() {
+    let air = Air::default();
+    let (chip_a, chip_b) = (SimChip::new(), SimChip::new());
+    air.attach(&chip_a, [0.0, 0.0, 0.0]);
+    air.attach(&chip_b, [10.0, 0.0, 0.0]);
+    let (mut a, mut b) = (chip_a.radio(DEFAULT_PARAMS), chip_b.radio(DEFAULT_PARAMS));
+
+    embassy_futures::block_on(async {
+        a.configure().await.unwrap();
+        b.configure().await.unwrap();
+
+        let mut buf = [0; 32];
+        let (received, ()) = join(b.receive(&mut buf), async {
+            a.send(b"ping").await.unwrap();
+            assert_eq!(air.in_flight(), 1);
+            air.flush();
+        })
+        .await;
+
+        let (len, status) = received.unwrap().unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        // 13 dBm minus roughly 60 dB of free space path loss over 10 m.
+        assert_eq!(status.rssi_sync, 94);
+    });
+
+    // SF12 at 200 kHz takes a while.
+    assert!(air.now().as_millis() > 100);
+    assert_eq!(air.in_flight(), 0);
+}
+
+#[test]
+fn air_propagation_delay() {
+    let air = Air::default();
+    let (chip_a, chip_b) = (SimChip::new(), SimChip::new());
+    let node_a = air.attach(&chip_a, [0.0, 0.0, 0.0]);
+    let node_b = air.attach(&chip_b, [0.0, 0.0, 0.0]);
+    air.set_path_loss(node_a, node_b, Some(70.0));
+    let (mut a, mut b) = (chip_a.radio(DEFAULT_PARAMS), chip_b.radio(DEFAULT_PARAMS));
+
+    embassy_futures::block_on(async {
+        a.configure().await.unwrap();
+        b.configure().await.unwrap();
+        b.listen(32).await.unwrap();
+
+        // Side by side, the packet arrives as it ends.
+        a.send(b"near").await.unwrap();
+        air.flush();
+        let airtime = air.now();
+        assert!(chip_b.irq_status().contains(Irq::RxDone));
+        b.read_packet(&mut [0; 32]).await.unwrap().unwrap();
+
+        // 3 km away it takes another 10 us.
+        air.set_position(node_b, [3000.0, 0.0, 0.0]);
+        a.send(b"far").await.unwrap();
+        air.advance(airtime);
+        assert_eq!(air.in_flight(), 0);
+        assert!(!chip_b.irq_status().contains(Irq::RxDone));
+        air.advance(Duration::from_micros(11));
+        assert!(chip_b.irq_status().contains(Irq::RxDone));
+    });
+}
+
+#[test]
+fn air_collision() {
+    let air = Air::new(AirParams::default());
+    let chips = [SimChip::new(), SimChip::new(), SimChip::new()];
+    let near = air.attach(&chips[0], [0.0, 0.0, 0.0]);
+    let far = air.attach(&chips[1], [0.0, 0.0, 0.0]);
+    let receiver = air.attach(&chips[2], [0.0, 0.0, 0.0]);
+    let [mut a, mut b, mut c] = chips.each_ref().map(|chip| chip.radio(DEFAULT_PARAMS));
+
+    embassy_futures::block_on(async {
+        a.configure().await.unwrap();
+        b.configure().await.unwrap();
+        c.configure().await.unwrap();
+
+        // Equally strong packets destroy each other.
+        air.set_path_loss(near, receiver, Some(70.0));
+        air.set_path_loss(far, receiver, Some(70.0));
+        c.ll()
+            .set_dio_irq_params()
+            .dispatch_async(|cmd| cmd.set_irq_mask(Irq::RxDone.bits()))
+            .await
+            .unwrap();
+        c.ll()
+            .set_rx()
+            .dispatch_async(|cmd| cmd.set_period_base_count(ll::RxTimeoutBaseCount::Continuous))
+            .await
+            .unwrap();
+        a.send(b"a").await.unwrap();
+        b.send(b"b").await.unwrap();
+        air.flush();
+        assert!(!chips[2].irq_status().contains(Irq::RxDone));
+
+        // The stronger packet is captured.
+        air.set_path_loss(far, receiver, Some(90.0));
+        a.send(b"a").await.unwrap();
+        b.send(b"b").await.unwrap();
+        air.flush();
+        assert!(chips[2].irq_status().contains(Irq::RxDone));
+        assert_eq!(chips[2].buffer()[0], b'a');
+    });
+}
+
+#[test]
+fn air_ranging() {
+    let air = Air::default();
+    let (chip_a, chip_b) = (SimChip::new(), SimChip::new());
+    air.attach(&chip_a, [0.0, 0.0, 0.0]);
+    air.attach(&chip_b, [0.0, 100.0, 0.0]);
+    let (mut a, mut b) = (chip_a.radio(DEFAULT_PARAMS), chip_b.radio(DEFAULT_PARAMS));
+
+    embassy_futures::block_on(async {
+        for (radio, role) in [
+            (&mut a, ll::RangingRole::Initiator),
+            (&mut b, ll::RangingRole::Responder),
+        ] {
+            radio.configure().await.unwrap();
+            radio.probe(ChipVariant::Sx1280).await.unwrap();
+            radio
+                .ll()
+                .set_packet_type()
+                .dispatch_async(|cmd| cmd.set_value(ll::PacketType::Ranging))
+                .await
+                .unwrap();
+            radio.set_ranging_role(role).await.unwrap();
+            radio
+                .ll()
+                .set_dio_irq_params()
+                .dispatch_async(|cmd| {
+                    cmd.set_irq_mask(Irq::all().bits());
+                    cmd.set_dio_1_mask(Irq::all().bits());
+                })
+                .await
+                .unwrap();
+        }
+
+        b.ll()
+            .ranging_device_address()
+            .write_async(|reg| reg.set_value(0x1234))
+            .await
+            .unwrap();
+        b.ll()
+            .set_rx()
+            .dispatch_async(|cmd| cmd.set_period_base_count(ll::RxTimeoutBaseCount::SingleMode))
+            .await
+            .unwrap();
+
+        a.ll()
+            .ranging_request_address()
+            .write_async(|reg| reg.set_value(0x1234))
+            .await
+            .unwrap();
+        a.ll()
+            .set_tx()
+            .dispatch_async(|cmd| cmd.set_period_base_count(ll::TxTimeoutBaseCount::SingleMode))
+            .await
+            .unwrap();
+        air.flush();
+
+        assert!(
+            chip_b
+                .irq_status()
+                .contains(Irq::RangingResponderResponseDone)
+        );
+        assert!(
+            chip_a
+                .irq_status()
+                .contains(Irq::RangingInitiatorResultValid)
+        );
+
+        // Distance = result * 150 / (2^12 * BW in MHz)
+        let result = a.ll().ranging_result().read_async().await.unwrap().value();
+        let distance = result as f64 * 150.0 / (4096.0 * 0.203125);
+        assert!((distance - 100.0).abs() < 0.5);
+    });
+}